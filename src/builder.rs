@@ -0,0 +1,335 @@
+//! Configures how a WACZ is created from WARC files.
+//!
+//! [`WACZ::from_file`] covers the simple case, the [`WaczBuilder`]
+//! sets everything else:
+//!
+//! ```
+//! # use std::{error::Error, path::Path};
+//! # use wacksy::{WACZ, indexer::IndexFormat};
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! let wacz_object = WACZ::builder()
+//!     .warc_file(Path::new("tests/example.warc.gz"))
+//!     .title("Example Domain")
+//!     .description("A crawl of example.com")
+//!     .index_format(IndexFormat::ZipNum { lines_per_block: 3000 })
+//!     .compression_method("indexes/", rawzip::CompressionMethod::Deflate)
+//!     .build()?;
+//! let zipped_wacz: Vec<u8> = wacz_object.zip()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::{DateTime, FixedOffset};
+use rawzip::CompressionMethod;
+use std::{
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    WACZ, WaczError,
+    datapackage::{DataPackage, DataPackageResource},
+    indexer::{
+        ErrorPolicy, Index, IndexFormat, IndexOptions, IndexingError, PageFilter, RequestPairs,
+    },
+};
+
+/// How each file in the zip archive is compressed.
+///
+/// Every file uses the default method, which is `Store` (no compression),
+/// unless its path matches one of the entries. An entry matches a file
+/// with exactly that path, or any file under it if the entry ends in
+/// `/`, and later entries take precedence over earlier ones. Only
+/// `Store` and `Deflate` are supported when writing.
+///
+/// WARC files are usually gzipped already, so there is little point
+/// deflating them, but a large CDXJ index shrinks a lot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZipCompression {
+    /// The compression method for files which do not match any entry
+    pub default: CompressionMethod,
+    /// Compression methods for particular paths or directories
+    pub entries: Vec<(String, CompressionMethod)>,
+}
+impl Default for ZipCompression {
+    fn default() -> Self {
+        return Self {
+            default: CompressionMethod::Store,
+            entries: Vec::new(),
+        };
+    }
+}
+impl ZipCompression {
+    /// Returns the compression method for the file at this path in the archive.
+    #[must_use]
+    pub fn method_for(&self, file_path: &str) -> CompressionMethod {
+        return self
+            .entries
+            .iter()
+            .rev()
+            .find(|(entry_path, _)| {
+                return entry_path == file_path
+                    || (entry_path.ends_with('/') && file_path.starts_with(entry_path.as_str()));
+            })
+            .map_or(self.default, |(_, compression_method)| {
+                return *compression_method;
+            });
+    }
+}
+
+/// Builds a [`WACZ`] with options, see [`WACZ::builder`].
+#[derive(Default)]
+pub struct WaczBuilder<'reader> {
+    warc_file_paths: Vec<PathBuf>,
+    warc_readers: Vec<(PathBuf, Box<dyn Read + 'reader>)>,
+    index_format: IndexFormat,
+    index_options: IndexOptions,
+    compression: ZipCompression,
+    created: Option<DateTime<FixedOffset>>,
+    title: Option<String>,
+    description: Option<String>,
+    main_page_url: Option<String>,
+    main_page_date: Option<String>,
+    modified: Option<String>,
+}
+impl<'reader> WaczBuilder<'reader> {
+    /// Add a WARC file on disk to the WACZ.
+    #[must_use]
+    pub fn warc_file(mut self, warc_file_path: &Path) -> Self {
+        self.warc_file_paths.push(warc_file_path.to_path_buf());
+        return self;
+    }
+    /// Add several WARC files on disk to the WACZ, in order.
+    #[must_use]
+    pub fn warc_files(mut self, warc_file_paths: &[PathBuf]) -> Self {
+        self.warc_file_paths.extend_from_slice(warc_file_paths);
+        return self;
+    }
+    /// Add a WARC file from anything which implements `Read`, under the
+    /// given file name. The name is used in the archive and the index, and
+    /// the content is treated as gzipped if it ends in `.gz`. The content
+    /// is held in memory until the WACZ is written, and WARC files added
+    /// this way come after any WARC files on disk.
+    #[must_use]
+    pub fn warc_reader(mut self, warc_file_name: &str, warc_reader: impl Read + 'reader) -> Self {
+        self.warc_readers
+            .push((PathBuf::from(warc_file_name), Box::new(warc_reader)));
+        return self;
+    }
+    /// Set the [format](IndexFormat) of the index, by default this is a plain CDXJ index.
+    #[must_use]
+    pub const fn index_format(mut self, index_format: IndexFormat) -> Self {
+        self.index_format = index_format;
+        return self;
+    }
+    /// Set what happens to records which cannot be indexed, by default
+    /// they are skipped, see [`ErrorPolicy`].
+    #[must_use]
+    pub const fn error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.index_options.error_policy = error_policy;
+        return self;
+    }
+    /// Find the original capture of each revisit record among all the
+    /// WARC files, see [`CDXJIndex::resolve_revisits`](crate::indexer::CDXJIndex::resolve_revisits).
+    #[must_use]
+    pub const fn resolve_revisits(mut self, resolve_revisits: bool) -> Self {
+        self.index_options.resolve_revisits = resolve_revisits;
+        return self;
+    }
+    /// Set which records are listed as pages, see [`PageFilter`].
+    #[must_use]
+    pub fn page_filter(mut self, page_filter: PageFilter) -> Self {
+        self.index_options.page_filter = page_filter;
+        return self;
+    }
+    /// Set the compression method for a file in the archive, or for every
+    /// file in a directory if the path ends in `/`. See [`ZipCompression`].
+    #[must_use]
+    pub fn compression_method(
+        mut self,
+        file_path: &str,
+        compression_method: CompressionMethod,
+    ) -> Self {
+        self.compression
+            .entries
+            .push((file_path.to_owned(), compression_method));
+        return self;
+    }
+    /// Set the compression method for every file without its own.
+    #[must_use]
+    pub const fn default_compression_method(
+        mut self,
+        compression_method: CompressionMethod,
+    ) -> Self {
+        self.compression.default = compression_method;
+        return self;
+    }
+    /// Set when the WACZ was created, instead of using the current time.
+    /// See [`DataPackageBuilder::created`](crate::datapackage::DataPackageBuilder::created).
+    #[must_use]
+    pub const fn created(mut self, created: DateTime<FixedOffset>) -> Self {
+        self.created = Some(created);
+        return self;
+    }
+    /// Set the title of the web archive.
+    #[must_use]
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_owned());
+        return self;
+    }
+    /// Set a longer description of the web archive.
+    #[must_use]
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_owned());
+        return self;
+    }
+    /// Set the url of the page replay tools should open first,
+    /// by default this is the first page in the pages index.
+    #[must_use]
+    pub fn main_page_url(mut self, main_page_url: &str) -> Self {
+        self.main_page_url = Some(main_page_url.to_owned());
+        return self;
+    }
+    /// Set when the main page was archived, in RFC 3339 format.
+    #[must_use]
+    pub fn main_page_date(mut self, main_page_date: &str) -> Self {
+        self.main_page_date = Some(main_page_date.to_owned());
+        return self;
+    }
+    /// Set when the WACZ was last changed, in RFC 3339 format.
+    #[must_use]
+    pub fn modified(mut self, modified: &str) -> Self {
+        self.modified = Some(modified.to_owned());
+        return self;
+    }
+    /// # Build WACZ
+    ///
+    /// Indexes every WARC file, and wraps the indexes and
+    /// WARC files up into a datapackage and [`WACZ`] struct.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WaczError`], see [`WACZ::from_file`]. A WARC file
+    /// added from a reader which cannot be read returns an
+    /// [indexing error](IndexingError::WarcFileError).
+    pub fn build(self) -> Result<WACZ, WaczError> {
+        // requests are paired with responses, and revisits are
+        // resolved, once every WARC file is indexed
+        let mut request_pairs = RequestPairs::default();
+        let mut index = match Index::index_files_in_run(
+            &self.warc_file_paths,
+            &self.index_options,
+            &mut request_pairs,
+        ) {
+            Ok(index) => index,
+            Err(indexing_error) => return Err(WaczError::IndexingError(indexing_error)),
+        };
+
+        let mut warc_resources: Vec<DataPackageResource> =
+            Vec::with_capacity(self.warc_readers.len());
+        for (warc_file_name, mut warc_reader) in self.warc_readers {
+            let mut warc_bytes: Vec<u8> = Vec::new();
+            if let Err(io_error) = warc_reader.read_to_end(&mut warc_bytes) {
+                return Err(WaczError::IndexingError(IndexingError::WarcFileError(
+                    io_error,
+                )));
+            }
+            match Index::index_reader_in_run(
+                warc_bytes.as_slice(),
+                &warc_file_name,
+                &self.index_options,
+                &mut request_pairs,
+            ) {
+                Ok(reader_index) => index.merge(reader_index),
+                Err(indexing_error) => return Err(WaczError::IndexingError(indexing_error)),
+            }
+            let Some(file_name) = warc_file_name.file_name() else {
+                return Err(WaczError::IndexingError(IndexingError::WarcFileError(
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("cannot get file name from {}", warc_file_name.display()),
+                    ),
+                )));
+            };
+            let path: PathBuf = Path::new("archive").join(file_name);
+            match DataPackageResource::new(&path, &warc_bytes) {
+                Ok(warc_resource) => warc_resources.push(warc_resource),
+                Err(datapackage_error) => {
+                    return Err(WaczError::DataPackageError(datapackage_error));
+                }
+            }
+        }
+
+        index.finish_run(request_pairs, &self.index_options);
+
+        let mut datapackage_builder =
+            DataPackage::builder(&self.warc_file_paths, &index).index_format(self.index_format);
+        for warc_resource in warc_resources {
+            datapackage_builder = datapackage_builder.warc_resource(warc_resource);
+        }
+        if let Some(created) = self.created {
+            datapackage_builder = datapackage_builder.created(created);
+        }
+        if let Some(title) = &self.title {
+            datapackage_builder = datapackage_builder.title(title);
+        }
+        if let Some(description) = &self.description {
+            datapackage_builder = datapackage_builder.description(description);
+        }
+        if let Some(main_page_url) = &self.main_page_url {
+            datapackage_builder = datapackage_builder.main_page_url(main_page_url);
+        }
+        if let Some(main_page_date) = &self.main_page_date {
+            datapackage_builder = datapackage_builder.main_page_date(main_page_date);
+        }
+        if let Some(modified) = &self.modified {
+            datapackage_builder = datapackage_builder.modified(modified);
+        }
+        let datapackage = match datapackage_builder.build() {
+            Ok(datapackage) => datapackage,
+            Err(datapackage_error) => return Err(WaczError::DataPackageError(datapackage_error)),
+        };
+        let datapackage_digest = match datapackage.digest() {
+            Ok(digest) => digest,
+            Err(digest_error) => return Err(WaczError::DataPackageError(digest_error)),
+        };
+
+        return Ok(WACZ {
+            datapackage,
+            datapackage_digest,
+            cdxj_index: index.cdxj,
+            pages_index: index.pages,
+            compression: self.compression,
+            datapackage_file: None,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn compression_method_for_path() {
+        let compression = ZipCompression {
+            default: CompressionMethod::Store,
+            entries: vec![
+                ("indexes/".to_owned(), CompressionMethod::Deflate),
+                ("indexes/index.cdx.gz".to_owned(), CompressionMethod::Store),
+            ],
+        };
+        assert_eq!(
+            compression.method_for("indexes/index.cdxj"),
+            CompressionMethod::Deflate
+        );
+        assert_eq!(
+            compression.method_for("indexes/index.cdx.gz"),
+            CompressionMethod::Store
+        );
+        assert_eq!(
+            compression.method_for("archive/example.warc.gz"),
+            CompressionMethod::Store
+        );
+    }
+}
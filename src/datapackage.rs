@@ -12,10 +12,12 @@
 //!   "wacz_version": "1.1.1",
 //!   "created": "2025-05-16T11:03:03.499792020+01:00",
 //!   "software": "wacksy 0.0.2",
+//!   "mainPageURL": "https://example.com/",
+//!   "mainPageDate": "2025-04-23T12:10:42Z",
 //!   "resources": [
 //!     {
-//!       "name": "data.warc",
-//!       "path": "archive/data.warc",
+//!       "name": "example.warc.gz",
+//!       "path": "archive/example.warc.gz",
 //!       "hash": "sha256:210d0810aaf4a4aba556f97bc7fc497d176a8c171d8edab3390e213a41bed145",
 //!       "bytes": 4599
 //!     },
@@ -29,13 +31,24 @@
 //! }
 //! ```
 
-use chrono::Local;
+use chrono::{DateTime, FixedOffset, Local};
 use serde::{Deserialize, Serialize};
 use serde_json;
 use sha2::{Digest as _, Sha256};
-use std::{error::Error, ffi::OsStr, fmt, fs, path::Path};
+use std::{
+    env,
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufReader, Read, Seek as _, SeekFrom},
+    path::{Path, PathBuf},
+};
 
-use crate::{WACZ_VERSION, indexer::Index};
+use crate::{
+    WACZ_VERSION,
+    indexer::{Index, IndexFormat},
+    reader::ZipEntryContent,
+};
 
 /// The main datapackage struct.
 #[derive(Serialize, Deserialize)]
@@ -44,10 +57,33 @@ pub struct DataPackage {
     pub profile: String,
     /// See [`WACZ_VERSION`] constant.
     pub wacz_version: String,
-    /// WACZ creation date, this is set to local datetime in [RFC 3399 format](https://rfc3339.date/).
+    /// WACZ creation date in [RFC 3399 format](https://rfc3339.date/), see [`current_time`].
     pub created: String,
     /// The name of the software used to create the WACZ file, in this case `wacksy 0.0.2`.
     pub software: String,
+    /// A title for the web archive, shown by replay tools.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// A longer description of the web archive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The url of the page replay tools should open first.
+    #[serde(
+        rename = "mainPageURL",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub main_page_url: Option<String>,
+    /// When the main page was archived, in RFC 3339 format.
+    #[serde(
+        rename = "mainPageDate",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub main_page_date: Option<String>,
+    /// When the WACZ was last changed, in RFC 3339 format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
     /// List of file names, paths, sizes and fixity for all files contained in the WACZ.
     pub resources: Vec<DataPackageResource>,
 }
@@ -60,17 +96,188 @@ pub struct DataPackageResource {
     pub path: String,
     pub hash: String,
     pub bytes: usize,
-    /// The raw content of the resource in bytes,
-    /// not passed through to serde when serialising to json.
+    /// The content of the resource, not passed
+    /// through to serde when serialising to json.
     #[serde(skip)]
-    pub content: Vec<u8>,
+    pub content: ResourceContent,
+}
+
+/// Where the content of a [resource](DataPackageResource) comes from.
+///
+/// Small files like the indexes are held in memory, but WARC files
+/// can be very large, so these are left on disk, or in the WACZ they
+/// were [read](crate::reader) from, and only read when they are
+/// [written out](crate::WACZ::write_to).
+#[derive(Debug)]
+pub enum ResourceContent {
+    /// The raw content of the resource in bytes.
+    Bytes(Vec<u8>),
+    /// A path to a file on disk containing the resource.
+    File(PathBuf),
+    /// A file inside an existing WACZ.
+    ZipEntry(ZipEntryContent),
+}
+impl Default for ResourceContent {
+    fn default() -> Self {
+        return Self::Bytes(Vec::new());
+    }
+}
+impl ResourceContent {
+    /// # Open resource content
+    ///
+    /// Returns a reader over the content of the resource,
+    /// opening the file if the content is on disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the file cannot be opened.
+    pub fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        match self {
+            Self::Bytes(bytes) => return Ok(Box::new(bytes.as_slice())),
+            Self::File(file_path) => return Ok(Box::new(BufReader::new(File::open(file_path)?))),
+            Self::ZipEntry(zip_entry) => return zip_entry.reader(),
+        }
+    }
+    /// # Open resource content at an offset
+    ///
+    /// The same as [`ResourceContent::reader`], but starts `offset` bytes
+    /// into the content, seeking to it where possible rather than reading
+    /// everything before it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the file cannot be opened or seeked.
+    pub fn reader_from(&self, offset: u64) -> io::Result<Box<dyn Read + '_>> {
+        match self {
+            Self::Bytes(bytes) => {
+                let offset = usize::try_from(offset).unwrap_or(usize::MAX);
+                return Ok(Box::new(bytes.get(offset..).unwrap_or_default()));
+            }
+            Self::File(file_path) => {
+                let mut file = File::open(file_path)?;
+                file.seek(SeekFrom::Start(offset))?;
+                return Ok(Box::new(BufReader::new(file)));
+            }
+            Self::ZipEntry(zip_entry) => return zip_entry.reader_from(offset),
+        }
+    }
+}
+
+/// Wraps a reader and hashes everything read through it,
+/// so a resource can be hashed while it is being copied.
+pub struct HashingReader<Reader> {
+    reader: Reader,
+    hasher: Sha256,
+    bytes_read: u64,
+}
+impl<Reader: Read> HashingReader<Reader> {
+    pub fn new(reader: Reader) -> Self {
+        return Self {
+            reader,
+            hasher: Sha256::new(),
+            bytes_read: 0,
+        };
+    }
+    /// Returns the hash, formatted the same way as in the
+    /// datapackage, along with the number of bytes read.
+    pub fn finish(self) -> (String, u64) {
+        return (
+            format!("sha256:{:x}", self.hasher.finalize()),
+            self.bytes_read,
+        );
+    }
+}
+impl<Reader: Read> Read for HashingReader<Reader> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.reader.read(buffer)?;
+        self.hasher.update(&buffer[..bytes_read]);
+        self.bytes_read += bytes_read as u64;
+        return Ok(bytes_read);
+    }
 }
 
 /// A digest of the datapackage file itself.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct DataPackageDigest {
     pub path: String,
     pub hash: String,
+    /// A signature over the hash, if the WACZ has been signed, see
+    /// the [WACZ Auth spec](https://specs.webrecorder.net/wacz-auth/0.1.0/).
+    #[serde(
+        rename = "signedData",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub signed_data: Option<SignedData>,
+}
+impl DataPackageDigest {
+    /// Makes an unsigned digest of the bytes of a `datapackage.json` file.
+    pub(crate) fn new(datapackage_bytes: &[u8]) -> Self {
+        return Self {
+            path: "datapackage.json".to_owned(),
+            hash: format!("sha256:{:x}", Sha256::digest(datapackage_bytes)),
+            signed_data: None,
+        };
+    }
+}
+
+/// The `signedData` block of a signed [datapackage digest](DataPackageDigest).
+///
+/// In anonymous mode the hash is signed with a local key and the public
+/// key is included. In domain mode the public key is replaced by a
+/// certificate chain for the domain, along with a trusted timestamp.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedData {
+    /// The hash which was signed, the same as the digest hash.
+    pub hash: String,
+    /// Base64-encoded ECDSA signature of the hash.
+    pub signature: String,
+    /// Base64-encoded public key, in anonymous mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// When the signature was made, in RFC 3339 format.
+    pub created: String,
+    /// The software used to sign the WACZ.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub software: Option<String>,
+    /// The domain of the signer, in domain mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    /// PEM certificate chain for the domain, in domain mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain_cert: Option<String>,
+    /// Trusted timestamp of the signature, in domain mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_signature: Option<String>,
+    /// PEM certificate chain for the timestamp, in domain mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_cert: Option<String>,
+    /// Certificate cross-signing the domain certificate, in domain mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cross_signed_cert: Option<String>,
+}
+
+/// # Current time
+///
+/// Returns the time used for the `created` field of a new datapackage,
+/// and for the timestamps of the files in the zip archive.
+///
+/// This is the local time, unless the `SOURCE_DATE_EPOCH` environment
+/// variable is set to a number of seconds since the Unix epoch, following
+/// the [reproducible builds](https://reproducible-builds.org/specs/source-date-epoch/)
+/// convention. With it set, the same WARC files always produce a
+/// byte-for-byte identical WACZ.
+#[must_use]
+pub fn current_time() -> DateTime<FixedOffset> {
+    let source_date_epoch = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch_seconds| return epoch_seconds.trim().parse::<i64>().ok())
+        .and_then(|epoch_seconds| return DateTime::from_timestamp(epoch_seconds, 0));
+    match source_date_epoch {
+        Some(source_date) => return source_date.fixed_offset(),
+        None => return Local::now().fixed_offset(),
+    }
 }
 
 impl Default for DataPackage {
@@ -78,8 +285,13 @@ impl Default for DataPackage {
         return Self {
             profile: "data-package".to_owned(),
             wacz_version: WACZ_VERSION.to_owned(),
-            created: Local::now().to_rfc3339(),
+            created: current_time().to_rfc3339(),
             software: format!("wacksy {}", env!("CARGO_PKG_VERSION")),
+            title: None,
+            description: None,
+            main_page_url: None,
+            main_page_date: None,
+            modified: None,
             resources: Vec::with_capacity(512),
         };
     }
@@ -89,36 +301,120 @@ impl DataPackage {
     ///
     /// Composes the data package and adds resources to it.
     ///
+    /// Each WARC file is added under `archive/` with its own file name,
+    /// matching the `filename` field in the CDX(J) index. The index is
+    /// added in the given [format](IndexFormat). The main page is set to
+    /// the first page in the pages index, use [`DataPackage::builder`]
+    /// to set it yourself, or to add a title and description.
+    ///
     /// # Errors
     ///
     /// Will return a `DataPackageError` relating to any
     /// resource if there is anything wrong with the filename
-    /// or path of a resource.
-    pub fn new(warc_file_path: &Path, index: &Index) -> Result<Self, DataPackageError> {
-        let mut data_package = Self::default();
+    /// or path of a resource, or if two WARC files have the
+    /// same name.
+    pub fn new(
+        warc_file_paths: &[PathBuf],
+        index: &Index,
+        index_format: IndexFormat,
+    ) -> Result<Self, DataPackageError> {
+        return Self::builder(warc_file_paths, index)
+            .index_format(index_format)
+            .build();
+    }
 
-        let warc_file_bytes = match fs::read(warc_file_path) {
-            Ok(bytes) => bytes,
-            Err(error) => return Err(DataPackageError::FileReadError(error)),
+    /// # Datapackage builder
+    ///
+    /// Starts building a datapackage for these WARC files and their
+    /// index, so the descriptive metadata can be set:
+    ///
+    /// ```
+    /// # use std::{error::Error, path::PathBuf};
+    /// # use wacksy::{datapackage::DataPackage, indexer::Index};
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// let warc_file_paths = [PathBuf::from("tests/example.warc.gz")];
+    /// let index = Index::index_files(&warc_file_paths)?;
+    /// let datapackage = DataPackage::builder(&warc_file_paths, &index)
+    ///     .title("Example Domain")
+    ///     .description("A crawl of example.com")
+    ///     .build()?;
+    /// assert_eq!(datapackage.main_page_url.as_deref(), Some("https://example.com/"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn builder<'builder>(
+        warc_file_paths: &'builder [PathBuf],
+        index: &'builder Index,
+    ) -> DataPackageBuilder<'builder> {
+        return DataPackageBuilder {
+            warc_file_paths,
+            index,
+            warc_resources: Vec::new(),
+            index_format: IndexFormat::default(),
+            created: None,
+            title: None,
+            description: None,
+            main_page_url: None,
+            main_page_date: None,
+            modified: None,
         };
+    }
 
-        // add warc file to datapackage
-        let path: &Path = if warc_file_path.extension() == Some(OsStr::new("gz")) {
-            Path::new("archive/data.warc.gz")
-        } else {
-            Path::new("archive/data.warc")
-        };
-        Self::add_resource(
-            &mut data_package,
-            DataPackageResource::new(path, &warc_file_bytes)?,
-        );
+    /// Add every resource to the datapackage, this is
+    /// everything apart from the descriptive metadata.
+    fn with_resources(
+        warc_file_paths: &[PathBuf],
+        warc_resources: Vec<DataPackageResource>,
+        index: &Index,
+        index_format: IndexFormat,
+    ) -> Result<Self, DataPackageError> {
+        let mut data_package = Self::default();
 
-        // add cdxj file to datapackage
-        let path: &Path = Path::new("indexes/index.cdxj");
-        Self::add_resource(
-            &mut data_package,
-            DataPackageResource::new(path, &index.cdxj.to_string().into_bytes())?,
-        );
+        // add warc files to datapackage, these are hashed
+        // from disk rather than read into memory
+        for warc_file_path in warc_file_paths {
+            let Some(warc_file_name) = warc_file_path.file_name() else {
+                return Err(DataPackageError::FileNameError(format!(
+                    "cannot get file name from {}",
+                    warc_file_path.display()
+                )));
+            };
+            let path: PathBuf = Path::new("archive").join(warc_file_name);
+            let resource = DataPackageResource::from_file(&path, warc_file_path)?;
+            Self::add_warc_resource(&mut data_package, resource)?;
+        }
+        for resource in warc_resources {
+            Self::add_warc_resource(&mut data_package, resource)?;
+        }
+
+        match index_format {
+            IndexFormat::Cdxj => {
+                // add cdxj file to datapackage
+                let path: &Path = Path::new("indexes/index.cdxj");
+                Self::add_resource(
+                    &mut data_package,
+                    DataPackageResource::new(path, &index.cdxj.to_string().into_bytes())?,
+                );
+            }
+            IndexFormat::ZipNum { lines_per_block } => {
+                // add compressed index and secondary index to datapackage
+                let zipnum_index = match index.cdxj.to_zipnum(lines_per_block) {
+                    Ok(zipnum_index) => zipnum_index,
+                    Err(error) => return Err(DataPackageError::IndexCompressionError(error)),
+                };
+                let path: &Path = Path::new("indexes/index.cdx.gz");
+                Self::add_resource(
+                    &mut data_package,
+                    DataPackageResource::new(path, &zipnum_index.compressed_index)?,
+                );
+                let path: &Path = Path::new("indexes/index.idx");
+                Self::add_resource(
+                    &mut data_package,
+                    DataPackageResource::new(path, zipnum_index.secondary_index.as_bytes())?,
+                );
+            }
+        }
 
         // add pages file to datapackage
         let path: &Path = Path::new("pages/pages.jsonl");
@@ -130,6 +426,26 @@ impl DataPackage {
         return Ok(data_package);
     }
 
+    /// Adds a WARC file to the datapackage, as long as
+    /// there is not already a WARC file with the same name.
+    fn add_warc_resource(
+        data_package: &mut Self,
+        resource: DataPackageResource,
+    ) -> Result<(), DataPackageError> {
+        if data_package
+            .resources
+            .iter()
+            .any(|existing_resource| return existing_resource.path == resource.path)
+        {
+            return Err(DataPackageError::FileNameError(format!(
+                "more than one WARC file is named {}",
+                resource.file_name
+            )));
+        }
+        Self::add_resource(data_package, resource);
+        return Ok(());
+    }
+
     /// Takes a `DataPackage` struct and pushes a resource to the
     /// 'resources' field.
     fn add_resource(data_package: &mut Self, resource: DataPackageResource) {
@@ -147,12 +463,7 @@ impl DataPackage {
     /// deserialising the data package to a vector.
     pub fn digest(&self) -> Result<DataPackageDigest, DataPackageError> {
         match serde_json::to_vec(&self) {
-            Ok(datapackage_as_vec) => {
-                return Ok(DataPackageDigest {
-                    path: "datapackage.json".to_owned(),
-                    hash: format!("sha256:{:x}", Sha256::digest(datapackage_as_vec)),
-                });
-            }
+            Ok(datapackage_as_vec) => return Ok(DataPackageDigest::new(&datapackage_as_vec)),
             Err(serde_error) => {
                 return Err(DataPackageError::SerialisationError(serde_error));
             }
@@ -160,6 +471,110 @@ impl DataPackage {
     }
 }
 
+/// Builds a [`DataPackage`] with descriptive metadata, see [`DataPackage::builder`].
+pub struct DataPackageBuilder<'builder> {
+    warc_file_paths: &'builder [PathBuf],
+    warc_resources: Vec<DataPackageResource>,
+    index: &'builder Index,
+    index_format: IndexFormat,
+    created: Option<DateTime<FixedOffset>>,
+    title: Option<String>,
+    description: Option<String>,
+    main_page_url: Option<String>,
+    main_page_date: Option<String>,
+    modified: Option<String>,
+}
+impl DataPackageBuilder<'_> {
+    /// Set when the WACZ was created, instead of using [`current_time`].
+    /// This is also used for the timestamps of the files in the zip archive,
+    /// so setting it makes the WACZ reproducible.
+    #[must_use]
+    pub const fn created(mut self, created: DateTime<FixedOffset>) -> Self {
+        self.created = Some(created);
+        return self;
+    }
+    /// Add a WARC file which is not on disk, such as one held in memory.
+    /// Its path should be under `archive/`, and it is listed after
+    /// the WARC files passed to [`DataPackage::builder`].
+    #[must_use]
+    pub fn warc_resource(mut self, warc_resource: DataPackageResource) -> Self {
+        self.warc_resources.push(warc_resource);
+        return self;
+    }
+    /// Set the [format](IndexFormat) of the index, by default this is a plain CDXJ index.
+    #[must_use]
+    pub const fn index_format(mut self, index_format: IndexFormat) -> Self {
+        self.index_format = index_format;
+        return self;
+    }
+    /// Set the title of the web archive.
+    #[must_use]
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_owned());
+        return self;
+    }
+    /// Set a longer description of the web archive.
+    #[must_use]
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_owned());
+        return self;
+    }
+    /// Set the url of the page replay tools should open first.
+    #[must_use]
+    pub fn main_page_url(mut self, main_page_url: &str) -> Self {
+        self.main_page_url = Some(main_page_url.to_owned());
+        return self;
+    }
+    /// Set when the main page was archived, in RFC 3339 format.
+    #[must_use]
+    pub fn main_page_date(mut self, main_page_date: &str) -> Self {
+        self.main_page_date = Some(main_page_date.to_owned());
+        return self;
+    }
+    /// Set when the WACZ was last changed, in RFC 3339 format.
+    #[must_use]
+    pub fn modified(mut self, modified: &str) -> Self {
+        self.modified = Some(modified.to_owned());
+        return self;
+    }
+    /// # Build datapackage
+    ///
+    /// Adds the resources and metadata to the datapackage. If no main
+    /// page url has been set, the url and date of the first page in the
+    /// pages index are used instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `DataPackageError`, see [`DataPackage::new`].
+    pub fn build(self) -> Result<DataPackage, DataPackageError> {
+        let mut data_package = DataPackage::with_resources(
+            self.warc_file_paths,
+            self.warc_resources,
+            self.index,
+            self.index_format,
+        )?;
+        if let Some(created) = self.created {
+            data_package.created = created.to_rfc3339();
+        }
+        data_package.title = self.title;
+        data_package.description = self.description;
+        data_package.modified = self.modified;
+        if self.main_page_url.is_some() {
+            data_package.main_page_url = self.main_page_url;
+            data_package.main_page_date = self.main_page_date;
+        } else if let Some(first_page) = self.index.pages.iter().next() {
+            data_package.main_page_url = Some(first_page.url.to_string());
+            data_package.main_page_date = Some(
+                self.main_page_date
+                    .unwrap_or_else(|| return first_page.timestamp.to_rfc3339()),
+            );
+        } else {
+            data_package.main_page_date = self.main_page_date;
+        }
+        return Ok(data_package);
+    }
+}
+
 impl DataPackageResource {
     /// # Instantiate datapackage resource
     ///
@@ -172,6 +587,57 @@ impl DataPackageResource {
     /// resource file path or file name are missing or cannot
     /// be converted to string.
     pub fn new(path: &Path, file_bytes: &[u8]) -> Result<Self, DataPackageError> {
+        let (file_name, path) = Self::names_from_path(path)?;
+
+        return Ok(Self {
+            file_name,
+            path,
+            hash: format!("sha256:{:x}", Sha256::digest(file_bytes)),
+            bytes: file_bytes.len(),
+            content: ResourceContent::Bytes(file_bytes.to_vec()),
+        });
+    }
+
+    /// # Instantiate datapackage resource from a file
+    ///
+    /// The same as [`DataPackageResource::new`], but streams the file
+    /// at `source_file_path` through the hasher instead of holding it
+    /// in memory. The file is left on disk until it is written out.
+    ///
+    /// # Errors
+    ///
+    /// Will return a `DataPackageError` if the resource file path
+    /// or file name are missing, or if the file cannot be read.
+    pub fn from_file(path: &Path, source_file_path: &Path) -> Result<Self, DataPackageError> {
+        let (file_name, path) = Self::names_from_path(path)?;
+
+        let source_file = match File::open(source_file_path) {
+            Ok(source_file) => source_file,
+            Err(error) => return Err(DataPackageError::FileReadError(error)),
+        };
+        let mut hashing_reader = HashingReader::new(BufReader::new(source_file));
+        if let Err(error) = io::copy(&mut hashing_reader, &mut io::sink()) {
+            return Err(DataPackageError::FileReadError(error));
+        }
+        let (hash, bytes) = hashing_reader.finish();
+        let Ok(bytes) = usize::try_from(bytes) else {
+            return Err(DataPackageError::FileReadError(io::Error::new(
+                io::ErrorKind::FileTooLarge,
+                format!("{} is too large", source_file_path.display()),
+            )));
+        };
+
+        return Ok(Self {
+            file_name,
+            path,
+            hash,
+            bytes,
+            content: ResourceContent::File(source_file_path.to_path_buf()),
+        });
+    }
+
+    /// Get the file name and path of the resource as strings.
+    fn names_from_path(path: &Path) -> Result<(String, String), DataPackageError> {
         let file_name = match path.file_name() {
             Some(file_name) => match file_name.to_str() {
                 Some(file_name) => file_name.to_owned(),
@@ -197,13 +663,7 @@ impl DataPackageResource {
             }
         };
 
-        return Ok(Self {
-            file_name,
-            path,
-            hash: format!("sha256:{:x}", Sha256::digest(file_bytes)),
-            bytes: file_bytes.len(),
-            content: file_bytes.to_vec(),
-        });
+        return Ok((file_name, path));
     }
 }
 
@@ -213,6 +673,7 @@ pub enum DataPackageError {
     FilePathError(String),
     FileReadError(std::io::Error),
     SerialisationError(serde_json::Error),
+    IndexCompressionError(std::io::Error),
 }
 impl fmt::Display for DataPackageError {
     fn fmt(&self, message: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -229,6 +690,9 @@ impl fmt::Display for DataPackageError {
             Self::SerialisationError(error_message) => {
                 return write!(message, "Serialisation error: {error_message}");
             }
+            Self::IndexCompressionError(error_message) => {
+                return write!(message, "Could not compress index: {error_message}");
+            }
         }
     }
 }
@@ -236,7 +700,9 @@ impl Error for DataPackageError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             Self::SerialisationError(parse_error) => return Some(parse_error),
-            Self::FileReadError(read_error) => return Some(read_error),
+            Self::FileReadError(read_error) | Self::IndexCompressionError(read_error) => {
+                return Some(read_error);
+            }
             Self::FilePathError(_) | Self::FileNameError(_) => return None,
         }
     }
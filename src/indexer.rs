@@ -1,13 +1,24 @@
 //! Reads the WARC file and composes a CDX(J) index.
 
+use std::cmp::Ordering;
 use std::ffi::OsStr;
 use std::fmt;
-use std::path::Path;
-use warc::{BufferedBody, Record, RecordType, WarcReader};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use warc::{BufferedBody, Record, RecordType};
 
 mod indexing_errors;
 pub use indexing_errors::IndexingError;
+mod indexing_warning;
+pub use indexing_warning::{IndexingWarning, SkippedFrom};
+mod index_options;
+pub use index_options::{ErrorPolicy, IndexOptions};
+mod page_filter;
+pub use page_filter::{PageFilter, PagePredicate};
 mod page_record;
+mod page_title;
 pub use page_record::PageRecord;
 mod record_timestamp;
 pub use record_timestamp::RecordTimestamp;
@@ -15,17 +26,35 @@ mod warc_filename;
 pub use warc_filename::WarcFilename;
 mod record_digest;
 pub use record_digest::RecordDigest;
+mod http_response;
+pub use http_response::{HttpRequestHead, HttpResponseHead};
+mod mime_sniffing;
+pub use mime_sniffing::{is_binary, sniff_mime};
 mod record_content_type;
 pub use record_content_type::RecordContentType;
 mod record_url;
 pub use record_url::RecordUrl;
 mod record_status;
 pub use record_status::RecordStatus;
+mod record_revisit;
+pub use record_revisit::{RecordRevisit, RevisitOriginal};
+mod record_request;
+pub use record_request::RecordRequest;
+pub(crate) use record_request::RequestPairs;
+mod record_location;
+pub use record_location::{LocatedRecord, RecordLocation};
+mod cdxj_line;
+mod query;
+pub use query::{CDXJQuery, MatchType};
+mod zipnum_index;
+pub use zipnum_index::{ZIPNUM_INDEX_FILENAME, ZIPNUM_LINES_PER_BLOCK, ZipNumIndex};
 
 pub struct Index {
     pub cdxj: CDXJIndex,
     pub pages: PageIndex,
     pub records_read: NumberOfRecordsRead,
+    /// Records which were skipped over, see [`IndexingWarning`]
+    pub warnings: Vec<IndexingWarning>,
 }
 
 impl Index {
@@ -34,103 +63,362 @@ impl Index {
     /// This function sets off looping through the
     /// records to build the CDXJ and Pages.jsonl file.
     ///
+    /// The offset and length of each record is taken from where it sits
+    /// in the file on disk, for gzipped WARC files this is the gzip
+    /// member containing the record. See [`RecordLocation`].
+    ///
+    /// Records which cannot be indexed are skipped, and listed in
+    /// [`Index::warnings`] rather than stopping the indexer.
+    ///
     /// # Errors
     ///
-    /// Returns a [file io error](IndexingError::WarcFileError) from
-    /// `WarcReader::from_path`/`from_path_gzip` in case of any problem reading
-    /// the WARC file. An [unrecoverable error](IndexingError::CriticalRecordError)
+    /// Returns a [file io error](IndexingError::WarcFileError) in case
+    /// of any problem opening the WARC file. An [unrecoverable error](IndexingError::CriticalRecordError)
     /// when reading the WARC record will stop the indexer and propogate
     /// all the way up to the top.
     pub fn index_file(warc_file_path: &Path) -> Result<Self, IndexingError> {
-        // this looping function accepts a generic type which
-        // this allows us to pass in both gzipped and non-gzipped records
-        fn loop_over_records<
-            RecordIterator: Iterator<Item = Result<Record<BufferedBody>, warc::Error>>,
-        >(
-            file_records: RecordIterator,
-            warc_file_path: &Path,
-        ) -> Result<Index, IndexingError> {
-            let mut record_count: usize = 0;
-            let mut byte_counter: u64 = 0;
-            let mut cdxj_index: Vec<CDXJIndexRecord> = Vec::with_capacity(1024);
-            let mut page_index: Vec<PageRecord> = Vec::with_capacity(1024);
-
-            for record in file_records.enumerate() {
-                record_count = record.0 + 1; // enumerate is zero-indexed, so add 1 here to compensate
-                match record.1 {
-                    Ok(record) => {
-                        match CDXJIndexRecord::new(&record, byte_counter, warc_file_path) {
-                            Ok(processed_record) => {
-                                // if the record was successfully indexed,
-                                // add it to the index
-                                cdxj_index.push(processed_record);
-                                // now try creating a page record
-                                match PageRecord::new(&record) {
-                                    Ok(processed_record) => {
-                                        page_index.push(processed_record);
-                                    }
-                                    Err(err) => eprintln!(
-                                        "Could not create page record for warc record {record_count} with id {}: {err}",
-                                        record.warc_id()
-                                    ),
-                                }
-                            }
-                            Err(err) => {
-                                eprintln!(
-                                    // Any error with the record means we have to
-                                    // skip over it and move on to the next one.
-                                    "Could not create cdxj record for warc record {record_count} with id {}: {err}",
-                                    record.warc_id()
-                                );
-                            }
-                        }
+        return Self::index_file_with_options(warc_file_path, &IndexOptions::default());
+    }
 
-                        // Get the length of the record body in content_length,
-                        // added to the length of the unwrapped record header
-                        let record_length: u64 = record.content_length()
-                            + u64::try_from(record.into_raw_parts().0.to_string().len()).unwrap();
+    /// # Index with options
+    ///
+    /// The same as [`Index::index_file`], but with [`IndexOptions`],
+    /// such as the [`ErrorPolicy`] for records which cannot be indexed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`IndexingError`], see [`Index::index_file`]. With a
+    /// strict error policy, the first record which cannot be indexed
+    /// returns a [`RecordError`](IndexingError::RecordError).
+    pub fn index_file_with_options(
+        warc_file_path: &Path,
+        index_options: &IndexOptions,
+    ) -> Result<Self, IndexingError> {
+        match File::open(warc_file_path) {
+            Ok(warc_file) => {
+                return Self::index_reader_with_options(warc_file, warc_file_path, index_options);
+            }
+            Err(file_read_error) => return Err(IndexingError::WarcFileError(file_read_error)),
+        }
+    }
 
-                        // increment the byte counter after processing the record
-                        byte_counter = byte_counter.wrapping_add(record_length);
-                    }
-                    Err(warc_error) => {
-                        return Err(IndexingError::CriticalRecordError(
-                            warc_error,
-                            record_count,
-                            byte_counter,
-                        ));
-                    }
+    /// # Index from a reader
+    ///
+    /// The same as [`Index::index_file`], but reads the WARC file from
+    /// anything which implements `Read`, such as a network stream or a
+    /// byte slice. The WARC file name is used for the `filename` field of
+    /// each CDX(J) record, and the reader is treated as gzipped if the
+    /// name ends in `.gz`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [unrecoverable error](IndexingError::CriticalRecordError)
+    /// if a WARC record cannot be read, see [`Index::index_file`].
+    pub fn index_reader<Reader: Read>(
+        warc_reader: Reader,
+        warc_file_name: &Path,
+    ) -> Result<Self, IndexingError> {
+        return Self::index_reader_with_options(
+            warc_reader,
+            warc_file_name,
+            &IndexOptions::default(),
+        );
+    }
+
+    /// # Index from a reader with options
+    ///
+    /// The same as [`Index::index_reader`], but with [`IndexOptions`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`IndexingError`], see [`Index::index_file_with_options`].
+    pub fn index_reader_with_options<Reader: Read>(
+        warc_reader: Reader,
+        warc_file_name: &Path,
+        index_options: &IndexOptions,
+    ) -> Result<Self, IndexingError> {
+        let mut request_pairs = RequestPairs::default();
+        let mut index = Self::index_reader_in_run(
+            warc_reader,
+            warc_file_name,
+            index_options,
+            &mut request_pairs,
+        )?;
+        index.finish_run(request_pairs, index_options);
+        return Ok(index);
+    }
+
+    /// # Index multiple files
+    ///
+    /// Indexes each WARC file in turn with [`Index::index_file`], and
+    /// merges the results into a single index. The CDX(J) records are
+    /// sorted by searchable url and timestamp once they are merged, and
+    /// the `filename` of each record points at the WARC file it came from.
+    /// Requests are paired with their responses across all the files, as
+    /// a crawler may write them to different WARC files.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first [`IndexingError`] from any of the WARC files,
+    /// see [`Index::index_file`].
+    pub fn index_files(warc_file_paths: &[PathBuf]) -> Result<Self, IndexingError> {
+        return Self::index_files_with_options(warc_file_paths, &IndexOptions::default());
+    }
+
+    /// # Index multiple files with options
+    ///
+    /// The same as [`Index::index_files`], but with [`IndexOptions`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the first [`IndexingError`] from any of the WARC files,
+    /// see [`Index::index_file_with_options`].
+    pub fn index_files_with_options(
+        warc_file_paths: &[PathBuf],
+        index_options: &IndexOptions,
+    ) -> Result<Self, IndexingError> {
+        let mut request_pairs = RequestPairs::default();
+        let mut index =
+            Self::index_files_in_run(warc_file_paths, index_options, &mut request_pairs)?;
+        index.finish_run(request_pairs, index_options);
+        return Ok(index);
+    }
+
+    /// # Index files as part of a run
+    ///
+    /// Indexes each WARC file in turn, see [`Index::index_reader_in_run`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the first [`IndexingError`] from any of the WARC files,
+    /// see [`Index::index_file_with_options`].
+    pub(crate) fn index_files_in_run(
+        warc_file_paths: &[PathBuf],
+        index_options: &IndexOptions,
+        request_pairs: &mut RequestPairs,
+    ) -> Result<Self, IndexingError> {
+        let mut index = Self {
+            cdxj: CDXJIndex(Vec::with_capacity(1024)),
+            pages: PageIndex(Vec::with_capacity(1024)),
+            records_read: NumberOfRecordsRead(0),
+            warnings: Vec::new(),
+        };
+        for warc_file_path in warc_file_paths {
+            let warc_file = match File::open(warc_file_path) {
+                Ok(warc_file) => warc_file,
+                Err(file_read_error) => {
+                    return Err(IndexingError::WarcFileError(file_read_error));
                 }
-            }
+            };
+            index.merge(Self::index_reader_in_run(
+                warc_file,
+                warc_file_path,
+                index_options,
+                request_pairs,
+            )?);
+        }
+        return Ok(index);
+    }
 
-            return Ok(Index {
-                cdxj: CDXJIndex(cdxj_index),
-                pages: PageIndex(page_index),
-                records_read: NumberOfRecordsRead(record_count),
-            });
+    /// # Index a reader as part of a run
+    ///
+    /// Indexes one WARC file out of several which make up a single run,
+    /// such as the WARC files of a WACZ. Requests and responses can be
+    /// in different files, so these are added to `request_pairs` rather
+    /// than being paired, and revisits can refer to records in any file,
+    /// so these are not resolved. Call [`Index::finish_run`] once every
+    /// file has been indexed and merged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`IndexingError`], see [`Index::index_file_with_options`].
+    pub(crate) fn index_reader_in_run<Reader: Read>(
+        warc_reader: Reader,
+        warc_file_name: &Path,
+        index_options: &IndexOptions,
+        request_pairs: &mut RequestPairs,
+    ) -> Result<Self, IndexingError> {
+        let resync = index_options.error_policy == ErrorPolicy::Resync;
+        if warc_file_name.extension() == Some(OsStr::new("gz")) {
+            let file_records = record_location::gzipped_records(warc_reader, resync);
+            return loop_over_records(file_records, warc_file_name, index_options, request_pairs);
+        } else {
+            let file_records = record_location::uncompressed_records(warc_reader, resync);
+            return loop_over_records(file_records, warc_file_name, index_options, request_pairs);
         }
+    }
 
-        if warc_file_path.extension() == Some(OsStr::new("gz")) {
-            match WarcReader::from_path_gzip(warc_file_path) {
-                Ok(file_gzip) => {
-                    let file_records = file_gzip.iter_records();
-                    let index = loop_over_records(file_records, warc_file_path)?;
-                    return Ok(index);
-                }
-                Err(file_read_error) => return Err(IndexingError::WarcFileError(file_read_error)),
+    /// # Finish a run
+    ///
+    /// Pairs up the requests and responses from every WARC file in the
+    /// run, sorts the CDX(J) records, and resolves revisits if the
+    /// [`IndexOptions`] ask for it.
+    pub(crate) fn finish_run(&mut self, request_pairs: RequestPairs, index_options: &IndexOptions) {
+        // responses to POST requests are looked up with the request body
+        request_pairs.pair(&mut self.cdxj.0);
+        // records are read in WARC order, but CDX(J)
+        // consumers expect them in searchable order
+        self.cdxj.sort();
+        if index_options.resolve_revisits {
+            self.cdxj.resolve_revisits();
+        }
+    }
+
+    /// # Merge indexes
+    ///
+    /// Adds the records and pages from another index to this one, keeping
+    /// the CDX(J) records sorted. Pages are added after the existing pages.
+    pub fn merge(&mut self, other_index: Self) {
+        self.cdxj.merge(other_index.cdxj);
+        self.pages.0.extend(other_index.pages.0);
+        self.records_read.0 += other_index.records_read.0;
+        self.warnings.extend(other_index.warnings);
+    }
+}
+
+// this looping function accepts a generic type which
+// this allows us to pass in both gzipped and non-gzipped records
+fn loop_over_records<RecordIterator: Iterator<Item = LocatedRecord>>(
+    file_records: RecordIterator,
+    warc_file_path: &Path,
+    index_options: &IndexOptions,
+    request_pairs: &mut RequestPairs,
+) -> Result<Index, IndexingError> {
+    let error_policy = index_options.error_policy;
+    let page_filter = &index_options.page_filter;
+    let mut record_count: usize = 0;
+    let mut cdxj_index: Vec<CDXJIndexRecord> = Vec::with_capacity(1024);
+    let mut page_index: Vec<PageRecord> = Vec::with_capacity(1024);
+    let mut warnings: Vec<IndexingWarning> = Vec::new();
+    let warc_file_name = warc_file_path
+        .file_name()
+        .unwrap_or(warc_file_path.as_os_str())
+        .to_string_lossy()
+        .into_owned();
+
+    for (record_number, (record, location)) in file_records.enumerate() {
+        record_count = record_number + 1; // enumerate is zero-indexed, so add 1 here to compensate
+        let warning = |record_id: Option<&str>, skipped_from: SkippedFrom, error| {
+            return IndexingWarning {
+                warc_file_name: warc_file_name.clone(),
+                record_number: record_count,
+                record_id: record_id.map(str::to_owned),
+                location,
+                skipped_from,
+                error,
+            };
+        };
+        let record = match record {
+            Ok(record) => record,
+            Err(warc_error) if error_policy == ErrorPolicy::Resync => {
+                // the record iterator has already moved on to the
+                // next record, so only the damage needs reporting
+                warnings.push(warning(
+                    None,
+                    SkippedFrom::CdxjIndex,
+                    IndexingError::DamagedRecord(warc_error),
+                ));
+                continue;
             }
-        } else {
-            match WarcReader::from_path(warc_file_path) {
-                Ok(file_not_gzip) => {
-                    let file_records = file_not_gzip.iter_records();
-                    let index = loop_over_records(file_records, warc_file_path)?;
-                    return Ok(index);
+            Err(warc_error) => {
+                return Err(IndexingError::CriticalRecordError(
+                    warc_error,
+                    record_count,
+                    location.map_or(0, |location| return location.offset),
+                ));
+            }
+        };
+
+        // parse the HTTP response once, and share it
+        // between all the fields which need it
+        let response_head = HttpResponseHead::parse(record.body());
+        let processed_record = match location {
+            Some(location) => {
+                CDXJIndexRecord::new(&record, &response_head, location, warc_file_path)
+            }
+            // Records compressed into the same gzip member as
+            // another record cannot be located on their own.
+            None => Err(IndexingError::SharedGzipMember),
+        };
+        match processed_record {
+            Ok(mut processed_record) => {
+                // malformed revisit headers are left out of the
+                // record, but do not stop it being indexed
+                if let Some(revisit) = &mut processed_record.revisit {
+                    for header_error in revisit.header_errors.drain(..) {
+                        warnings.push(warning(
+                            Some(record.warc_id()),
+                            SkippedFrom::RevisitHeader,
+                            header_error,
+                        ));
+                    }
                 }
-                Err(file_read_error) => return Err(IndexingError::WarcFileError(file_read_error)),
+                // requests are never pages
+                if record.warc_type() != &RecordType::Request {
+                    match page_record(&record, &response_head, &processed_record, page_filter) {
+                        Ok(page) => {
+                            page_index.push(page);
+                        }
+                        Err(err) => {
+                            warnings.push(warning(
+                                Some(record.warc_id()),
+                                SkippedFrom::PageIndex,
+                                err,
+                            ));
+                        }
+                    }
+                }
+                // if the record was successfully indexed,
+                // add it to the index
+                request_pairs.add(&record, &processed_record);
+                cdxj_index.push(processed_record);
+            }
+            // some types of record are never indexed, so
+            // these are skipped whatever the error policy
+            Err(err @ IndexingError::UnindexableRecordType(_)) => {
+                warnings.push(warning(Some(record.warc_id()), SkippedFrom::CdxjIndex, err));
+            }
+            Err(err) if error_policy == ErrorPolicy::Strict => {
+                return Err(IndexingError::RecordError(Box::new(warning(
+                    Some(record.warc_id()),
+                    SkippedFrom::CdxjIndex,
+                    err,
+                ))));
+            }
+            // Any error with the record means we have to
+            // skip over it and move on to the next one.
+            Err(err) => {
+                warnings.push(warning(Some(record.warc_id()), SkippedFrom::CdxjIndex, err));
             }
         }
     }
+
+    return Ok(Index {
+        cdxj: CDXJIndex(cdxj_index),
+        pages: PageIndex(page_index),
+        records_read: NumberOfRecordsRead(record_count),
+        warnings,
+    });
 }
+
+/// Create a page record from a record which has been indexed,
+/// if its HTTP response could be parsed.
+fn page_record(
+    record: &Record<BufferedBody>,
+    response_head: &Result<HttpResponseHead, httparse::Error>,
+    cdxj_record: &CDXJIndexRecord,
+    page_filter: &PageFilter,
+) -> Result<PageRecord, IndexingError> {
+    match response_head {
+        Ok(response_head) => {
+            return PageRecord::from_cdxj_record(record, response_head, cdxj_record, page_filter);
+        }
+        Err(http_parsing_error) => {
+            return Err(IndexingError::HttpResponseError(*http_parsing_error));
+        }
+    }
+}
+
 pub struct NumberOfRecordsRead(usize);
 impl fmt::Display for NumberOfRecordsRead {
     fn fmt(&self, message: &mut fmt::Formatter) -> fmt::Result {
@@ -139,7 +427,60 @@ impl fmt::Display for NumberOfRecordsRead {
 }
 
 /// Contains a list of [CDX(J) records](CDXJIndexRecord).
+///
+/// Consumers of the index binary-search through it, so [the spec](https://specs.webrecorder.net/cdxj/0.1.0/#sorting)
+/// requires the lines to be sorted. Indexes created by the [indexer](Index)
+/// are always sorted, indexes read in from elsewhere can be checked with
+/// [`CDXJIndex::is_sorted`].
 pub struct CDXJIndex(Vec<CDXJIndexRecord>);
+impl CDXJIndex {
+    /// # Sort index
+    ///
+    /// Sorts the records by searchable url and then by timestamp, which
+    /// is the same order as sorting the lines of the index as plain text.
+    /// Records with the same url and timestamp are ordered by filename
+    /// and offset, so the order does not depend on the order of the input.
+    pub fn sort(&mut self) {
+        return self.0.sort_by(CDXJIndexRecord::sort_order);
+    }
+    /// # Check index is sorted
+    ///
+    /// Returns `true` if the records are in the order produced by
+    /// [`CDXJIndex::sort`].
+    #[must_use]
+    pub fn is_sorted(&self) -> bool {
+        return self.0.is_sorted_by(|first_record, second_record| {
+            return first_record.sort_order(second_record) != Ordering::Greater;
+        });
+    }
+    /// Returns the number of records in the index.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        return self.0.len();
+    }
+    /// Returns `true` if the index has no records.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        return self.0.is_empty();
+    }
+    /// # Resolve revisits
+    ///
+    /// Finds the original capture of each revisit record in the index,
+    /// and adds its location, along with its status and media type where
+    /// the revisit does not have them. Revisits without an original in
+    /// the index are left unresolved. See [`RecordRevisit`].
+    pub fn resolve_revisits(&mut self) {
+        return record_revisit::resolve_revisits(&mut self.0);
+    }
+    /// # Merge indexes
+    ///
+    /// Adds all the records from another index to this one,
+    /// and sorts the result so it stays valid.
+    pub fn merge(&mut self, other_index: Self) {
+        self.0.extend(other_index.0);
+        return self.sort();
+    }
+}
 impl fmt::Display for CDXJIndex {
     fn fmt(&self, message: &mut fmt::Formatter) -> fmt::Result {
         let index_string: String = self.0.iter().map(ToString::to_string).collect();
@@ -147,16 +488,87 @@ impl fmt::Display for CDXJIndex {
     }
 }
 
+/// How the CDX(J) index is written into the WACZ.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IndexFormat {
+    /// A plain text index at `indexes/index.cdxj`.
+    #[default]
+    Cdxj,
+    /// A [compressed index](ZipNumIndex) at `indexes/index.cdx.gz`, with
+    /// a secondary index at `indexes/index.idx`. This is better for large
+    /// archives, see [`ZIPNUM_LINES_PER_BLOCK`] for a sensible block size.
+    ZipNum { lines_per_block: usize },
+}
+
 pub struct PageIndex(Vec<PageRecord>);
+impl PageIndex {
+    /// Returns the number of pages in the index.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        return self.0.len();
+    }
+    /// Returns `true` if the index has no pages.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        return self.0.is_empty();
+    }
+    /// Returns an iterator over the pages in the index.
+    pub fn iter(&self) -> std::slice::Iter<'_, PageRecord> {
+        return self.0.iter();
+    }
+}
+impl<'index> IntoIterator for &'index PageIndex {
+    type Item = &'index PageRecord;
+    type IntoIter = std::slice::Iter<'index, PageRecord>;
+    fn into_iter(self) -> Self::IntoIter {
+        return self.0.iter();
+    }
+}
 impl fmt::Display for PageIndex {
     fn fmt(&self, message: &mut fmt::Formatter) -> fmt::Result {
         let index_string: String = self.0.iter().map(ToString::to_string).collect();
-        let header: &'static str = "{\"format\":\"json-pages-1.0\",\"id\":\"pages\",\"title\":\"All Pages\"}";
+        let header: &'static str =
+            "{\"format\":\"json-pages-1.0\",\"id\":\"pages\",\"title\":\"All Pages\"}";
         return write!(message, "{header}\n{index_string}");
     }
 }
 
+/// Read a pages.jsonl file back in, skipping the header line.
+impl FromStr for PageIndex {
+    type Err = IndexingError;
+    fn from_str(index: &str) -> Result<Self, Self::Err> {
+        let mut page_index: Vec<PageRecord> = Vec::with_capacity(1024);
+        for (line_number, line) in index.lines().enumerate() {
+            // the header line declares the format,
+            // it is the only line with this field
+            if line.trim().is_empty() || (line_number == 0 && line.contains("\"format\"")) {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(record) => page_index.push(record),
+                Err(parsing_error) => {
+                    return Err(IndexingError::IndexParsingError(format!(
+                        "line {} of pages index: {parsing_error}",
+                        line_number + 1
+                    )));
+                }
+            }
+        }
+        return Ok(Self(page_index));
+    }
+}
+
 /// A record which would make up a line in a [CDX(J) index](CDXJIndex).
+///
+/// Records can be written out and parsed back in as CDX(J) lines with
+/// `Display` and `FromStr`, and are serialised by serde to the same
+/// string. The `digest`, `mime` and `status` fields are set by the indexer
+/// for every record apart from requests, but are optional when reading
+/// indexes from other tools.
+///
+/// Requests which are not `GET` or `HEAD`, such as `POST`, are indexed
+/// along with their responses under a searchable url which includes the
+/// request body, see [`RecordRequest`].
 pub struct CDXJIndexRecord {
     /// The date and time when the web archive snapshot was created
     pub timestamp: RecordTimestamp,
@@ -164,41 +576,95 @@ pub struct CDXJIndexRecord {
     pub searchable_url: String,
     /// The URL that was archived
     pub url: RecordUrl,
-    /// A cryptographic hash for the HTTP response payload       
-    pub digest: RecordDigest,
+    /// A cryptographic hash for the HTTP response payload
+    pub digest: Option<RecordDigest>,
     /// The media type for the response payload
-    pub mime: RecordContentType,
+    pub mime: Option<RecordContentType>,
     /// The WARC file where the WARC record is located
     pub filename: WarcFilename,
-    /// The byte offset for the WARC record
+    /// The byte offset for the WARC record, or the gzip
+    /// member containing it if the WARC file is gzipped
     pub offset: u64,
-    /// The length in bytes of the WARC record
+    /// The length in bytes of the WARC record, or the gzip
+    /// member containing it if the WARC file is gzipped
     pub length: u64,
-    // The HTTP status code for the HTTP response
-    pub status: RecordStatus,
+    /// The HTTP status code for the HTTP response
+    pub status: Option<RecordStatus>,
+    /// The HTTP method of the request, if it is not `GET`
+    pub method: Option<String>,
+    /// The query parameters made from the request
+    /// body, which are part of the searchable url
+    pub request_body: Option<String>,
+    /// Where to find the original capture, if this is a revisit
+    pub revisit: Option<RecordRevisit>,
 }
 
 impl CDXJIndexRecord {
+    /// Compare two records by searchable url and timestamp,
+    /// falling back on filename and offset to break ties.
+    fn sort_order(&self, other_record: &Self) -> Ordering {
+        return self
+            .searchable_url
+            .cmp(&other_record.searchable_url)
+            .then_with(|| return self.timestamp.cmp(&other_record.timestamp))
+            .then_with(|| return self.filename.cmp(&other_record.filename))
+            .then_with(|| return self.offset.cmp(&other_record.offset));
+    }
     /// # Create CDXJ index record
     ///
     /// Takes a `Record<BufferedBody>` and parses it to extract all
-    /// the fields which make up a CDX(J) record.
+    /// the fields which make up a CDX(J) record. The offset and length
+    /// come from the [location](RecordLocation) of the record on disk.
+    /// The [HTTP response](HttpResponseHead) is parsed from the record
+    /// body beforehand, so it can be shared with the [`PageRecord`].
+    ///
+    /// Revisit records have the `warc/revisit` media type, and only have
+    /// a digest and status if these are in the record. The original
+    /// capture is not located until [`CDXJIndex::resolve_revisits`].
     ///
     /// # Errors
     ///
-    /// If the record is not a Warc `response`, `revisit`, `resource`, or `metadata`,
-    /// an `UnindexableRecordType` error is returned. Otherwise, returns corresponding
+    /// If the record is not a Warc `response`, `revisit`, `resource`, `metadata`,
+    /// or a `request` with a method other than `GET` or `HEAD`, an
+    /// `UnindexableRecordType` error is returned. An `HttpResponseError`
+    /// is returned if the HTTP response of a `response` record could not
+    /// be parsed. A `resource` or `metadata` record without an HTTP
+    /// response is indexed with the content type from its WARC header,
+    /// and no status. Otherwise, returns corresponding
     /// errors for each of the CDX(J) fields.
     pub fn new(
         record: &Record<BufferedBody>,
-        byte_counter: u64,
+        response_head: &Result<HttpResponseHead, httparse::Error>,
+        location: RecordLocation,
         warc_file_path: &Path,
     ) -> Result<Self, IndexingError> {
-        // first check whether the record is either
-        // a response, revisit, resource, or metadata
-        if [
+        // first check whether the record is either a revisit,
+        // or a response, resource, or metadata
+        if record.warc_type() == &RecordType::Revisit {
+            let url = RecordUrl::new(record)?;
+            let searchable_url = url.as_searchable_string()?;
+            let response_head = response_head.as_ref().ok();
+            return Ok(Self {
+                timestamp: RecordTimestamp::new(record)?,
+                url,
+                searchable_url,
+                // a revisit may not have a payload or HTTP block of its
+                // own, the status can be filled in from the original
+                digest: RecordDigest::new(record).ok(),
+                mime: Some(RecordContentType::from(
+                    RecordContentType::REVISIT.to_owned(),
+                )),
+                filename: WarcFilename::new(record, warc_file_path)?,
+                offset: location.offset,
+                length: location.length,
+                status: response_head
+                    .and_then(|response_head| return RecordStatus::new(response_head).ok()),
+                method: None,
+                request_body: None,
+                revisit: Some(RecordRevisit::new(record, response_head)),
+            });
+        } else if [
             RecordType::Response,
-            RecordType::Revisit,
             RecordType::Resource,
             RecordType::Metadata,
         ]
@@ -206,16 +672,57 @@ impl CDXJIndexRecord {
         {
             let url = RecordUrl::new(record)?;
             let searchable_url = url.as_searchable_string()?;
+            let (mime, status) = match response_head {
+                Ok(response_head) => (
+                    Some(RecordContentType::new(record, response_head)?),
+                    Some(RecordStatus::new(response_head)?),
+                ),
+                // resources and metadata may hold a file rather than an
+                // HTTP response, so there is only the WARC content type
+                Err(_) if record.warc_type() != &RecordType::Response => {
+                    (RecordContentType::from_warc_header(record).ok(), None)
+                }
+                Err(http_parsing_error) => {
+                    return Err(IndexingError::HttpResponseError(*http_parsing_error));
+                }
+            };
+            return Ok(Self {
+                timestamp: RecordTimestamp::new(record)?,
+                url,
+                searchable_url,
+                digest: Some(RecordDigest::new(record)?),
+                mime,
+                filename: WarcFilename::new(record, warc_file_path)?,
+                offset: location.offset,
+                length: location.length,
+                status,
+                method: None,
+                request_body: None,
+                revisit: None,
+            });
+        } else if record.warc_type() == &RecordType::Request {
+            let request = RecordRequest::new(record)?;
+            if !request.is_indexed() {
+                return Err(IndexingError::UnindexableRecordType(RecordType::Request));
+            }
+            let url = RecordUrl::new(record)?;
+            let searchable_url = url.as_searchable_string_with_request(&request.request_body)?;
             return Ok(Self {
                 timestamp: RecordTimestamp::new(record)?,
                 url,
                 searchable_url,
-                digest: RecordDigest::new(record)?,
-                mime: RecordContentType::new(record)?,
+                // request records do not usually have a payload digest
+                digest: RecordDigest::new(record).ok(),
+                mime: Some(RecordContentType::from(
+                    RecordContentType::REQUEST.to_owned(),
+                )),
                 filename: WarcFilename::new(record, warc_file_path)?,
-                offset: byte_counter,
-                length: record.content_length(),
-                status: RecordStatus::new(record)?,
+                offset: location.offset,
+                length: location.length,
+                status: None,
+                method: Some(request.method),
+                request_body: Some(request.request_body),
+                revisit: None,
             });
         } else {
             // if the record is not one of the types we want,
@@ -226,25 +733,3 @@ impl CDXJIndexRecord {
         }
     }
 }
-
-/// Display the record to json as shown in [the example in the
-/// spec](https://specs.webrecorder.net/cdxj/0.1.0/#example)
-///
-/// Could there be a better way to serialize this?
-impl fmt::Display for CDXJIndexRecord {
-    fn fmt(&self, message: &mut fmt::Formatter) -> fmt::Result {
-        return writeln!(
-            message,
-            "{} {} {{\"url\":\"{}\",\"digest\":\"{}\",\"mime\":\"{}\",\"offset\":{},\"length\":{},\"status\":{},\"filename\":\"{}\"}}",
-            self.searchable_url,
-            self.timestamp,
-            self.url,
-            self.digest,
-            self.mime,
-            self.offset,
-            self.length,
-            self.status,
-            self.filename
-        );
-    }
-}
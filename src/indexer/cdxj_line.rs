@@ -0,0 +1,345 @@
+use crate::indexer::{
+    CDXJIndex, CDXJIndexRecord, IndexingError, RecordContentType, RecordDigest, RecordRevisit,
+    RecordStatus, RecordTimestamp, RecordUrl, RevisitOriginal, WarcFilename,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::{fmt, str::FromStr};
+
+/// The json block of a CDX(J) line, with the fields in
+/// the same order as [the example in the spec](https://specs.webrecorder.net/cdxj/0.1.0/#example),
+/// followed by the `method` and `requestBody` fields used by pywb for
+/// requests other than `GET`, the fields locating the original
+/// capture of a revisit record, and the declared and sniffed media
+/// types of a record whose media type was sniffed.
+#[derive(Deserialize, Serialize)]
+struct CDXJJsonBlock {
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    digest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mime: Option<String>,
+    #[serde(deserialize_with = "number_or_string")]
+    offset: u64,
+    #[serde(deserialize_with = "number_or_string")]
+    length: u64,
+    #[serde(
+        default,
+        deserialize_with = "optional_number_or_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    status: Option<u16>,
+    filename: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    method: Option<String>,
+    #[serde(
+        default,
+        rename = "requestBody",
+        skip_serializing_if = "Option::is_none"
+    )]
+    request_body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
+    #[serde(
+        default,
+        rename = "refersToUrl",
+        skip_serializing_if = "Option::is_none"
+    )]
+    refers_to_url: Option<String>,
+    #[serde(
+        default,
+        rename = "refersToDate",
+        skip_serializing_if = "Option::is_none"
+    )]
+    refers_to_date: Option<String>,
+    #[serde(default, rename = "origMime", skip_serializing_if = "Option::is_none")]
+    original_mime: Option<String>,
+    #[serde(
+        default,
+        rename = "origFilename",
+        skip_serializing_if = "Option::is_none"
+    )]
+    original_filename: Option<String>,
+    #[serde(
+        default,
+        rename = "origOffset",
+        deserialize_with = "optional_number_or_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    original_offset: Option<u64>,
+    #[serde(
+        default,
+        rename = "origLength",
+        deserialize_with = "optional_number_or_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    original_length: Option<u64>,
+    #[serde(
+        default,
+        rename = "declaredMime",
+        skip_serializing_if = "Option::is_none"
+    )]
+    declared_mime: Option<String>,
+    #[serde(
+        default,
+        rename = "sniffedMime",
+        skip_serializing_if = "Option::is_none"
+    )]
+    sniffed_mime: Option<String>,
+}
+
+/// Some indexers, such as [cdxj-indexer](https://github.com/webrecorder/cdxj-indexer),
+/// write numeric fields as strings, so both need to be accepted.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString {
+    Number(u64),
+    String(String),
+}
+
+fn number_or_string<'de, Number, D>(deserializer: D) -> Result<Number, D::Error>
+where
+    Number: TryFrom<u64> + FromStr,
+    D: Deserializer<'de>,
+{
+    let parsed_number = match NumberOrString::deserialize(deserializer)? {
+        NumberOrString::Number(number) => Number::try_from(number).ok(),
+        NumberOrString::String(string) => string.parse().ok(),
+    };
+    match parsed_number {
+        Some(number) => return Ok(number),
+        None => return Err(de::Error::custom("expected a valid number")),
+    }
+}
+
+/// The same as `number_or_string`, but an empty
+/// value written as `-` is read as missing.
+fn optional_number_or_string<'de, Number, D>(deserializer: D) -> Result<Option<Number>, D::Error>
+where
+    Number: TryFrom<u64> + FromStr,
+    D: Deserializer<'de>,
+{
+    match Option::<NumberOrString>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(NumberOrString::String(string)) if string == "-" => return Ok(None),
+        Some(NumberOrString::Number(number)) => match Number::try_from(number) {
+            Ok(number) => return Ok(Some(number)),
+            Err(_) => return Err(de::Error::custom("expected a valid number")),
+        },
+        Some(NumberOrString::String(string)) => match string.parse() {
+            Ok(number) => return Ok(Some(number)),
+            Err(_) => return Err(de::Error::custom("expected a valid number")),
+        },
+    }
+}
+
+/// Parse a single CDX(J) line, made up of a searchable url,
+/// a 14-digit timestamp and a json block, separated by spaces.
+///
+/// Numeric fields in the json block can be written either as numbers
+/// or strings. The `digest`, `mime` and `status` fields can be left out,
+/// and any fields not listed in the spec are ignored.
+impl FromStr for CDXJIndexRecord {
+    type Err = IndexingError;
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut fields = line.trim_end().splitn(3, ' ');
+        let (Some(searchable_url), Some(timestamp), Some(json_block)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            return Err(IndexingError::IndexParsingError(
+                "expected a searchable url, timestamp and json block".to_owned(),
+            ));
+        };
+        let json_block: CDXJJsonBlock = match serde_json::from_str(json_block) {
+            Ok(json_block) => json_block,
+            Err(parsing_error) => {
+                return Err(IndexingError::IndexParsingError(parsing_error.to_string()));
+            }
+        };
+
+        // any line with the revisit media type is a revisit,
+        // even if it has none of the other revisit fields
+        let revisit = if json_block.mime.as_deref() == Some("warc/revisit") {
+            Some(RecordRevisit {
+                profile: json_block.profile,
+                refers_to_url: match json_block.refers_to_url {
+                    Some(refers_to_url) => Some(RecordUrl::from_str(&refers_to_url)?),
+                    None => None,
+                },
+                refers_to_date: match json_block.refers_to_date {
+                    Some(refers_to_date) => Some(RecordTimestamp::from_str(&refers_to_date)?),
+                    None => None,
+                },
+                original_mime: json_block.original_mime.map(RecordContentType::from),
+                original: match (
+                    json_block.original_filename,
+                    json_block.original_offset,
+                    json_block.original_length,
+                ) {
+                    (Some(filename), Some(offset), Some(length)) => Some(RevisitOriginal {
+                        filename: WarcFilename::from(filename),
+                        offset,
+                        length,
+                    }),
+                    _ => None,
+                },
+                header_errors: Vec::new(),
+            })
+        } else {
+            None
+        };
+
+        return Ok(Self {
+            timestamp: RecordTimestamp::from_str(timestamp)?,
+            searchable_url: searchable_url.to_owned(),
+            url: RecordUrl::from_str(&json_block.url)?,
+            digest: json_block.digest.map(RecordDigest::from),
+            mime: json_block.mime.map(|mime| {
+                let mut mime = RecordContentType::from(mime);
+                if json_block.sniffed_mime.is_some() {
+                    mime.declared_mime = json_block.declared_mime;
+                    mime.sniffed_mime = json_block.sniffed_mime;
+                }
+                return mime;
+            }),
+            filename: WarcFilename::from(json_block.filename),
+            offset: json_block.offset,
+            length: json_block.length,
+            status: json_block.status.map(RecordStatus),
+            method: json_block.method,
+            request_body: json_block.request_body,
+            revisit,
+        });
+    }
+}
+
+/// Read a CDX(J) index back in, one [record](CDXJIndexRecord) per line.
+///
+/// Blank lines are ignored. Any line which cannot be parsed returns
+/// an [`IndexParsingError`](IndexingError::IndexParsingError) with
+/// the line number.
+impl FromStr for CDXJIndex {
+    type Err = IndexingError;
+    fn from_str(index: &str) -> Result<Self, Self::Err> {
+        let mut cdxj_index: Vec<CDXJIndexRecord> = Vec::with_capacity(1024);
+        for (line_number, line) in index.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match CDXJIndexRecord::from_str(line) {
+                Ok(record) => cdxj_index.push(record),
+                Err(parsing_error) => {
+                    return Err(IndexingError::IndexParsingError(format!(
+                        "line {} of CDXJ index: {parsing_error}",
+                        line_number + 1
+                    )));
+                }
+            }
+        }
+        return Ok(Self(cdxj_index));
+    }
+}
+
+/// Display the record to json as shown in [the example in the
+/// spec](https://specs.webrecorder.net/cdxj/0.1.0/#example)
+impl fmt::Display for CDXJIndexRecord {
+    fn fmt(&self, message: &mut fmt::Formatter) -> fmt::Result {
+        let revisit = self.revisit.as_ref();
+        let original = revisit.and_then(|revisit| return revisit.original.as_ref());
+        let sniffed_mime = self
+            .mime
+            .as_ref()
+            .filter(|mime| return mime.sniffed_mime.is_some());
+        let json_block = CDXJJsonBlock {
+            url: self.url.to_string(),
+            digest: self.digest.as_ref().map(ToString::to_string),
+            mime: self.mime.as_ref().map(ToString::to_string),
+            offset: self.offset,
+            length: self.length,
+            status: self.status.as_ref().map(|status| return status.0),
+            filename: self.filename.to_string(),
+            method: self.method.clone(),
+            request_body: self.request_body.clone(),
+            profile: revisit.and_then(|revisit| return revisit.profile.clone()),
+            refers_to_url: revisit
+                .and_then(|revisit| return revisit.refers_to_url.as_ref())
+                .map(ToString::to_string),
+            refers_to_date: revisit
+                .and_then(|revisit| return revisit.refers_to_date.as_ref())
+                .map(ToString::to_string),
+            original_mime: revisit
+                .and_then(|revisit| return revisit.original_mime.as_ref())
+                .map(ToString::to_string),
+            original_filename: original.map(|original| return original.filename.to_string()),
+            original_offset: original.map(|original| return original.offset),
+            original_length: original.map(|original| return original.length),
+            // the declared media type is only worth writing if it was not used
+            declared_mime: sniffed_mime.and_then(|mime| return mime.declared_mime.clone()),
+            sniffed_mime: sniffed_mime.and_then(|mime| return mime.sniffed_mime.clone()),
+        };
+        let Ok(json_block) = serde_json::to_string(&json_block) else {
+            return Err(fmt::Error);
+        };
+        return writeln!(
+            message,
+            "{} {} {json_block}",
+            self.searchable_url, self.timestamp
+        );
+    }
+}
+
+/// Serialise the record to a single CDX(J) line,
+/// without the newline at the end.
+impl Serialize for CDXJIndexRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.serialize_str(self.to_string().trim_end());
+    }
+}
+
+/// Deserialise the record from a single CDX(J) line.
+impl<'de> Deserialize<'de> for CDXJIndexRecord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let line = String::deserialize(deserializer)?;
+        match Self::from_str(&line) {
+            Ok(record) => return Ok(record),
+            Err(parsing_error) => return Err(de::Error::custom(parsing_error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn round_trip_cdxj_line() {
+        let line = "com,example)/ 20250423121042 {\"url\":\"https://example.com/\",\"digest\":\"sha256:ea8fac7c65fb589b0d53560f5251f74f9e9b243478dcb6b3ea79b5e36449c8d9\",\"mime\":\"text/html\",\"offset\":278,\"length\":1288,\"status\":200,\"filename\":\"example.warc.gz\"}\n";
+        let record = CDXJIndexRecord::from_str(line).unwrap();
+
+        assert_eq!(record.to_string(), line);
+    }
+
+    #[test]
+    fn cdxj_line_with_string_numbers_and_missing_fields() {
+        let line = "com,example)/ 20250423121042 {\"url\": \"urn:pageinfo:https://example.com/\", \"mime\": \"application/json\", \"offset\": \"4149\", \"length\": \"450\", \"filename\": \"example.warc.gz\", \"recordDigest\": \"sha256:0000\"}";
+        let record = CDXJIndexRecord::from_str(line).unwrap();
+
+        assert_eq!(record.offset, 4149);
+        assert_eq!(record.length, 450);
+        assert!(record.digest.is_none() && record.status.is_none());
+        assert_eq!(
+            record.to_string(),
+            "com,example)/ 20250423121042 {\"url\":\"urn:pageinfo:https://example.com/\",\"mime\":\"application/json\",\"offset\":4149,\"length\":450,\"filename\":\"example.warc.gz\"}\n"
+        );
+    }
+
+    #[test]
+    fn invalid_cdxj_line() {
+        let short_timestamp = "com,example)/ 2025 {\"url\":\"https://example.com/\",\"offset\":0,\"length\":1,\"filename\":\"example.warc.gz\"}";
+        let missing_offset = "com,example)/ 20250423121042 {\"url\":\"https://example.com/\",\"length\":1,\"filename\":\"example.warc.gz\"}";
+
+        assert!(CDXJIndexRecord::from_str(short_timestamp).is_err());
+        assert!(CDXJIndexRecord::from_str(missing_offset).is_err());
+        assert!(CDXJIndexRecord::from_str("com,example)/").is_err());
+    }
+}
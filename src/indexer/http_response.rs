@@ -0,0 +1,402 @@
+use libflate::{deflate, gzip, zlib};
+use std::{borrow::Cow, io::Read};
+
+/// Start with room for this many headers, which covers nearly every response.
+const INITIAL_HEADER_COUNT: usize = 64;
+
+/// Give up on responses with more headers than this.
+const MAX_HEADER_COUNT: usize = 4096;
+
+/// The status line and headers of an HTTP response, from the block of
+/// a WARC `response` or `revisit` record.
+///
+/// The indexer parses this once for each record, and passes it to each
+/// of the fields which need it, such as [`RecordStatus`](crate::indexer::RecordStatus)
+/// and [`RecordContentType`](crate::indexer::RecordContentType).
+///
+/// Parsing is lenient, as archived responses are whatever the server
+/// sent. Header names are matched without case, folded header values
+/// are joined onto one line, and there is no fixed limit on the number
+/// of headers. If the headers are cut off before the blank line, the
+/// headers read so far are kept and the body is empty.
+pub struct HttpResponseHead {
+    /// The HTTP status code, if the status line is complete
+    pub status: Option<u16>,
+    /// The reason phrase after the status code, such as `OK`
+    pub reason: Option<String>,
+    /// The HTTP headers, in the order they were archived
+    pub headers: Vec<(String, Vec<u8>)>,
+    /// The position in the record block where the payload starts
+    pub body_offset: usize,
+}
+
+impl HttpResponseHead {
+    /// # Parse HTTP response
+    ///
+    /// Parses the status line and headers at the start of `block`
+    /// with httparse, retrying with room for more headers if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `httparse::Error` if the block does not
+    /// start with a valid HTTP response.
+    pub fn parse(block: &[u8]) -> Result<Self, httparse::Error> {
+        let mut allow_folding = true;
+        loop {
+            let response_head = parse_with_header_room(|headers| {
+                let mut parser_config = httparse::ParserConfig::default();
+                parser_config
+                    .allow_obsolete_multiline_headers_in_responses(allow_folding)
+                    .allow_spaces_after_header_name_in_responses(true);
+                let mut response = httparse::Response::new(headers);
+                let body_offset = match parser_config.parse_response(&mut response, block)? {
+                    httparse::Status::Complete(header_length) => header_length,
+                    // a folded header might carry on past the end of a cut off
+                    // response, so it is never read, try again without folding
+                    httparse::Status::Partial if allow_folding => return Ok(None),
+                    httparse::Status::Partial => block.len(),
+                };
+                return Ok(Some(Self {
+                    status: response.code,
+                    reason: response.reason.map(str::to_owned),
+                    headers: owned_headers(response.headers),
+                    body_offset,
+                }));
+            })?;
+            match response_head {
+                Some(response_head) => return Ok(response_head),
+                None => allow_folding = false,
+            }
+        }
+    }
+    /// # Get HTTP header
+    ///
+    /// Returns the value of the first header with this
+    /// name, ignoring case, or `None` if it is not present.
+    #[must_use]
+    pub fn header(&self, header_name: &str) -> Option<&[u8]> {
+        return self.header_values(header_name).next();
+    }
+    /// Returns the values of every header with this name, ignoring case,
+    /// as a header such as `Set-Cookie` can appear more than once.
+    pub fn header_values(&self, header_name: &str) -> impl Iterator<Item = &[u8]> {
+        return header_values(&self.headers, header_name);
+    }
+    /// # Decode payload
+    ///
+    /// Returns the payload after the headers in `block`, with any
+    /// `Transfer-Encoding` and `Content-Encoding` undone, so chunked,
+    /// `gzip` and `deflate` payloads can be read.
+    ///
+    /// Some WARC writers store the payload already decoded but keep the
+    /// headers, so a payload which does not start with a chunk size is
+    /// treated as not chunked. A payload which is cut off is decoded as
+    /// far as it goes. Returns `None` if the payload uses an encoding
+    /// which cannot be decoded, such as `br`, or is not valid.
+    ///
+    /// At most `limit` bytes are returned. Decompression stops there, as
+    /// a small compressed payload can decode to something far larger,
+    /// while joining chunks never makes the payload any longer.
+    #[must_use]
+    pub fn decoded_payload<'block>(
+        &self,
+        block: &'block [u8],
+        limit: usize,
+    ) -> Option<Cow<'block, [u8]>> {
+        let mut payload = Cow::Borrowed(block.get(self.body_offset..).unwrap_or_default());
+        // encodings are listed in the order they were applied, and the
+        // transfer encoding comes last, so undo them in reverse
+        let encodings: Vec<String> = ["content-encoding", "transfer-encoding"]
+            .iter()
+            .flat_map(|header_name| return self.header_values(header_name))
+            .flat_map(|header_value| return header_value.split(|byte| return *byte == b','))
+            .map(|encoding| return String::from_utf8_lossy(encoding).trim().to_lowercase())
+            .filter(|encoding| return !encoding.is_empty())
+            .collect();
+        for encoding in encodings.iter().rev() {
+            let decoded_payload = match encoding.as_str() {
+                "identity" => continue,
+                "chunked" => match dechunk(&payload) {
+                    Some(decoded_payload) => decoded_payload,
+                    None => continue,
+                },
+                "gzip" | "x-gzip" => match gzip::MultiDecoder::new(payload.as_ref()) {
+                    Ok(mut decoder) => read_partial(&mut decoder, limit)?,
+                    Err(_) => return None,
+                },
+                // this should be zlib wrapped, but some servers send raw deflate
+                "deflate" => {
+                    match read_partial(&mut zlib::Decoder::new(payload.as_ref()).ok()?, limit) {
+                        Some(decoded_payload) => decoded_payload,
+                        None => read_partial(&mut deflate::Decoder::new(payload.as_ref()), limit)?,
+                    }
+                }
+                _ => return None,
+            };
+            payload = Cow::Owned(decoded_payload);
+        }
+        match payload {
+            Cow::Borrowed(payload) => {
+                return Some(Cow::Borrowed(payload.get(..limit).unwrap_or(payload)));
+            }
+            Cow::Owned(mut payload) => {
+                payload.truncate(limit);
+                return Some(Cow::Owned(payload));
+            }
+        }
+    }
+}
+
+/// The request line and headers of an HTTP request, from the block
+/// of a WARC `request` record.
+///
+/// This is parsed as leniently as an [`HttpResponseHead`], with no fixed
+/// limit on the number of headers, and invalid header lines skipped.
+pub struct HttpRequestHead {
+    /// The HTTP request method, if the request line is complete
+    pub method: Option<String>,
+    /// The HTTP headers, in the order they were archived
+    pub headers: Vec<(String, Vec<u8>)>,
+    /// The position in the record block where the body starts
+    pub body_offset: usize,
+}
+
+impl HttpRequestHead {
+    /// # Parse HTTP request
+    ///
+    /// Parses the request line and headers at the start of `block`
+    /// with httparse, retrying with room for more headers if needed.
+    /// If the headers are cut off, the headers read so far are kept
+    /// and the body is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `httparse::Error` if the block does not
+    /// start with a valid HTTP request.
+    pub fn parse(block: &[u8]) -> Result<Self, httparse::Error> {
+        return parse_with_header_room(|headers| {
+            let mut parser_config = httparse::ParserConfig::default();
+            parser_config
+                .allow_multiple_spaces_in_request_line_delimiters(true)
+                .ignore_invalid_headers_in_requests(true);
+            let mut request = httparse::Request::new(headers);
+            let body_offset = match parser_config.parse_request(&mut request, block)? {
+                httparse::Status::Complete(header_length) => header_length,
+                httparse::Status::Partial => block.len(),
+            };
+            return Ok(Self {
+                method: request.method.map(str::to_owned),
+                headers: owned_headers(request.headers),
+                body_offset,
+            });
+        });
+    }
+    /// Returns the values of every header with this name, ignoring case.
+    pub fn header_values(&self, header_name: &str) -> impl Iterator<Item = &[u8]> {
+        return header_values(&self.headers, header_name);
+    }
+    /// # Request body
+    ///
+    /// Returns the body after the headers in `block`, with the chunks
+    /// joined if the `Transfer-Encoding` is chunked. As with a response,
+    /// a body which does not start with a chunk size is left as it is.
+    #[must_use]
+    pub fn body<'block>(&self, block: &'block [u8]) -> Cow<'block, [u8]> {
+        let body = block.get(self.body_offset..).unwrap_or_default();
+        let is_chunked = self.header_values("transfer-encoding").any(|header_value| {
+            return header_value
+                .split(|byte| return *byte == b',')
+                .any(|encoding| return encoding.trim_ascii().eq_ignore_ascii_case(b"chunked"));
+        });
+        if is_chunked {
+            if let Some(dechunked_body) = dechunk(body) {
+                return Cow::Owned(dechunked_body);
+            }
+        }
+        return Cow::Borrowed(body);
+    }
+}
+
+/// Parse the head of an HTTP message with `parse`, retrying with
+/// room for more headers if there are too many to fit.
+fn parse_with_header_room<'block, Parsed>(
+    mut parse: impl FnMut(&mut [httparse::Header<'block>]) -> Result<Parsed, httparse::Error>,
+) -> Result<Parsed, httparse::Error> {
+    let mut header_count = INITIAL_HEADER_COUNT;
+    loop {
+        let mut headers = vec![httparse::EMPTY_HEADER; header_count];
+        match parse(&mut headers) {
+            Err(httparse::Error::TooManyHeaders) if header_count < MAX_HEADER_COUNT => {
+                header_count *= 2;
+            }
+            parsed => return parsed,
+        }
+    }
+}
+
+/// Copy the parsed headers, unfolding their values. If the message is
+/// cut off, the headers read so far are filled in, and the rest are
+/// left empty.
+fn owned_headers(headers: &[httparse::Header<'_>]) -> Vec<(String, Vec<u8>)> {
+    return headers
+        .iter()
+        .filter(|header| return !header.name.is_empty())
+        .map(|header| return (header.name.to_owned(), unfold(header.value)))
+        .collect();
+}
+
+/// Returns the values of every header with this name, ignoring case.
+fn header_values<'head>(
+    headers: &'head [(String, Vec<u8>)],
+    header_name: &str,
+) -> impl Iterator<Item = &'head [u8]> {
+    return headers
+        .iter()
+        .filter(move |(name, _)| return name.eq_ignore_ascii_case(header_name))
+        .map(|(_, value)| return value.as_slice());
+}
+
+/// Join the chunks of a chunked payload, or return `None` if the
+/// payload does not start with a chunk size.
+fn dechunk(payload: &[u8]) -> Option<Vec<u8>> {
+    let mut dechunked_payload = Vec::with_capacity(payload.len());
+    let mut remaining_payload = payload;
+    let mut is_chunked = false;
+    while let Some(line_end) = remaining_payload
+        .windows(2)
+        .position(|line_break| return line_break == b"\r\n")
+    {
+        // the size can be followed by chunk extensions after `;`
+        let chunk_size = str::from_utf8(&remaining_payload[..line_end])
+            .ok()
+            .and_then(|size_line| return size_line.split(';').next())
+            .and_then(|chunk_size| return usize::from_str_radix(chunk_size.trim(), 16).ok());
+        let Some(chunk_size) = chunk_size else {
+            break;
+        };
+        is_chunked = true;
+        if chunk_size == 0 {
+            break;
+        }
+        let chunk_start = line_end + 2;
+        let chunk_end = chunk_start
+            .saturating_add(chunk_size)
+            .min(remaining_payload.len());
+        dechunked_payload.extend_from_slice(&remaining_payload[chunk_start..chunk_end]);
+        remaining_payload = remaining_payload.get(chunk_end + 2..).unwrap_or_default();
+    }
+    if !is_chunked && !payload.is_empty() {
+        return None;
+    }
+    return Some(dechunked_payload);
+}
+
+/// Read as much as can be decoded, up to `limit` bytes, as archived
+/// payloads may be cut off.
+fn read_partial(decoder: &mut impl Read, limit: usize) -> Option<Vec<u8>> {
+    let mut decoded_payload = Vec::new();
+    let limit = u64::try_from(limit).unwrap_or(u64::MAX);
+    match decoder.take(limit).read_to_end(&mut decoded_payload) {
+        Ok(_) => return Some(decoded_payload),
+        Err(_) if !decoded_payload.is_empty() => return Some(decoded_payload),
+        Err(_) => return None,
+    }
+}
+
+/// Replace each line break in a folded header value, along with
+/// the whitespace around it, with a single space.
+fn unfold(header_value: &[u8]) -> Vec<u8> {
+    let mut unfolded_value = Vec::with_capacity(header_value.len());
+    let mut in_fold = false;
+    for byte in header_value {
+        if matches!(byte, b'\r' | b'\n') {
+            in_fold = true;
+            while unfolded_value.last().is_some_and(u8::is_ascii_whitespace) {
+                unfolded_value.pop();
+            }
+        } else if !(in_fold && matches!(byte, b' ' | b'\t')) {
+            if in_fold {
+                unfolded_value.push(b' ');
+                in_fold = false;
+            }
+            unfolded_value.push(*byte);
+        }
+    }
+    return unfolded_value;
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn folded_and_repeated_headers() {
+        let block = b"HTTP/1.1 200 OK\r\nContent-Type: text/html;\r\n charset=utf-8\r\nSet-Cookie: a=1\r\nset-cookie: b=2\r\n\r\n<p>";
+        let response_head = HttpResponseHead::parse(block).unwrap();
+
+        assert_eq!(response_head.status, Some(200));
+        assert_eq!(
+            response_head.header("content-type"),
+            Some(b"text/html; charset=utf-8".as_slice())
+        );
+        assert_eq!(response_head.header_values("SET-COOKIE").count(), 2);
+        assert_eq!(&block[response_head.body_offset..], b"<p>");
+    }
+
+    #[test]
+    fn more_than_64_headers() {
+        let header_lines: String = (0..100)
+            .map(|header_number| return format!("X-Header-{header_number}: {header_number}\r\n"))
+            .collect();
+        let block = format!("HTTP/1.1 200 OK\r\n{header_lines}Content-Type: text/plain\r\n\r\n");
+        let response_head = HttpResponseHead::parse(block.as_bytes()).unwrap();
+
+        assert_eq!(response_head.headers.len(), 101);
+        assert_eq!(
+            response_head.header("Content-Type"),
+            Some(b"text/plain".as_slice())
+        );
+    }
+
+    #[test]
+    fn chunked_gzip_payload() {
+        let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+        std::io::Write::write_all(&mut encoder, b"<title>example</title>").unwrap();
+        let gzipped_payload = encoder.finish().into_result().unwrap();
+        let mut block = format!(
+            "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n",
+            gzipped_payload.len()
+        )
+        .into_bytes();
+        block.extend_from_slice(&gzipped_payload);
+        block.extend_from_slice(b"\r\n0\r\n\r\n");
+        let response_head = HttpResponseHead::parse(&block).unwrap();
+
+        assert_eq!(
+            response_head.decoded_payload(&block, 1024).as_deref(),
+            Some(b"<title>example</title>".as_slice())
+        );
+        assert_eq!(
+            response_head.decoded_payload(&block, 7).as_deref(),
+            Some(b"<title>".as_slice())
+        );
+    }
+
+    #[test]
+    fn gzip_bomb_payload() {
+        let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+        std::io::Write::write_all(&mut encoder, &vec![0_u8; 4 * 1024 * 1024]).unwrap();
+        let gzipped_payload = encoder.finish().into_result().unwrap();
+        let mut block = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\n\r\n".to_vec();
+        block.extend_from_slice(&gzipped_payload);
+        let response_head = HttpResponseHead::parse(&block).unwrap();
+
+        assert_eq!(
+            response_head
+                .decoded_payload(&block, 1445)
+                .map(|payload| return payload.len()),
+            Some(1445)
+        );
+    }
+}
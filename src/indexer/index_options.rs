@@ -0,0 +1,33 @@
+use crate::indexer::PageFilter;
+
+/// What the indexer does when a record cannot be indexed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Stop on the first record which should be in the CDX(J) index but
+    /// cannot be indexed. Records which are never indexed, such as
+    /// `warcinfo` and `request` records, are still skipped, as are
+    /// records which are not pages.
+    Strict,
+    /// Skip any record which cannot be indexed and list it in the
+    /// [warnings](crate::indexer::Index::warnings), but stop if a record
+    /// cannot be read at all, as the rest of the file cannot be located.
+    #[default]
+    Lenient,
+    /// The same as `Lenient`, but when a record cannot be read, scan
+    /// forward to the next gzip member, or the next line starting
+    /// `WARC/1.` in an uncompressed file, and carry on indexing from
+    /// there. The damaged bytes are listed in the warnings.
+    Resync,
+}
+
+/// Options for the [indexer](crate::indexer::Index::index_file_with_options).
+#[derive(Clone, Debug, Default)]
+pub struct IndexOptions {
+    /// What to do with records which cannot be indexed
+    pub error_policy: ErrorPolicy,
+    /// Find the original capture of each revisit record among the
+    /// records being indexed, see [`CDXJIndex::resolve_revisits`](crate::indexer::CDXJIndex::resolve_revisits)
+    pub resolve_revisits: bool,
+    /// Which records are listed as pages
+    pub page_filter: PageFilter,
+}
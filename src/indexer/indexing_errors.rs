@@ -2,6 +2,8 @@ use std::error::Error;
 use std::fmt::{Display, Formatter, Result};
 use std::io;
 
+use crate::indexer::IndexingWarning;
+
 #[derive(Debug)]
 pub enum IndexingError {
     /// could not read timestamp from record
@@ -14,6 +16,10 @@ pub enum IndexingError {
     RecordUrlError(url::ParseError),
     /// could not parse HTTP status code
     RecordStatusError(String),
+    /// could not parse the HTTP response headers
+    HttpResponseError(httparse::Error),
+    /// could not parse the HTTP request
+    RecordRequestError(String),
     /// some value was missing
     ValueNotFound(String),
     /// this type of record can not be indexed
@@ -21,6 +27,14 @@ pub enum IndexingError {
     /// probkem
     WarcFileError(io::Error),
     CriticalRecordError(warc::Error, usize, u64),
+    /// could not parse a line from an existing index
+    IndexParsingError(String),
+    /// the record is in the same gzip member as the one before
+    SharedGzipMember,
+    /// the record could not be read, and was skipped over
+    DamagedRecord(warc::Error),
+    /// a record could not be indexed under a strict error policy
+    RecordError(Box<IndexingWarning>),
 }
 impl Display for IndexingError {
     fn fmt(&self, message: &mut Formatter<'_>) -> Result {
@@ -49,6 +63,12 @@ impl Display for IndexingError {
                     "Could not parse HTTP status: {parse_int_error_message}"
                 );
             }
+            Self::HttpResponseError(error_message) => {
+                return write!(message, "Could not parse HTTP response: {error_message}");
+            }
+            Self::RecordRequestError(error_message) => {
+                return write!(message, "Could not parse HTTP request: {error_message}");
+            }
             Self::ValueNotFound(error_message) => {
                 return write!(message, "Value not found: {error_message}");
             }
@@ -70,6 +90,21 @@ impl Display for IndexingError {
                     so the rest of the file cannot be indexed: {warc_error}"
                 );
             }
+            Self::IndexParsingError(error_message) => {
+                return write!(message, "Could not parse index: {error_message}");
+            }
+            Self::SharedGzipMember => {
+                return write!(
+                    message,
+                    "Record shares a gzip member with the previous record, so cannot be located"
+                );
+            }
+            Self::DamagedRecord(warc_error) => {
+                return write!(message, "Damaged record skipped: {warc_error}");
+            }
+            Self::RecordError(indexing_warning) => {
+                return write!(message, "{indexing_warning}");
+            }
         }
     }
 }
@@ -79,12 +114,19 @@ impl Error for IndexingError {
             Self::RecordTimestampError(parse_error) => return Some(parse_error),
             Self::RecordUrlError(parse_error) => return Some(parse_error),
             Self::WarcFileError(io_error) => return Some(io_error),
-            Self::CriticalRecordError(warc_error, ..) => return Some(warc_error),
+            Self::CriticalRecordError(warc_error, ..) | Self::DamagedRecord(warc_error) => {
+                return Some(warc_error);
+            }
+            Self::RecordError(indexing_warning) => return Some(&indexing_warning.error),
+            Self::HttpResponseError(http_parsing_error) => return Some(http_parsing_error),
             Self::ValueNotFound(_)
             | Self::RecordStatusError(_)
+            | Self::RecordRequestError(_)
             | Self::UnindexableRecordType(_)
             | Self::RecordContentTypeError(_)
-            | Self::WarcFilenameError(_) => return None,
+            | Self::WarcFilenameError(_)
+            | Self::IndexParsingError(_)
+            | Self::SharedGzipMember => return None,
         }
     }
 }
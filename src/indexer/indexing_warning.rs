@@ -0,0 +1,61 @@
+use crate::indexer::{RecordLocation, indexing_errors::IndexingError};
+use std::fmt;
+
+/// What was left out of the index because of a [warning](IndexingWarning).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkippedFrom {
+    /// The record was left out of both the CDX(J) index and the pages list.
+    CdxjIndex,
+    /// The record is in the CDX(J) index, but is not a page.
+    PageIndex,
+    /// The revisit record is in the CDX(J) index, but a malformed
+    /// `WARC-Refers-To` header was left out of it.
+    RevisitHeader,
+}
+
+/// A record the indexer skipped over, collected into the
+/// [`Index`](crate::indexer::Index) instead of stopping indexing.
+///
+/// Most WARC files contain records which are not meant to be indexed,
+/// such as `warcinfo` and `request` records, and most responses are not
+/// pages, so a list of warnings is normal and not a sign of a problem
+/// with the WARC file.
+#[derive(Debug)]
+pub struct IndexingWarning {
+    /// The name of the WARC file the record is in
+    pub warc_file_name: String,
+    /// The position of the record in the WARC file, starting from 1
+    pub record_number: usize,
+    /// The `WARC-Record-ID` of the record, if it could be read
+    pub record_id: Option<String>,
+    /// Where the record is in the WARC file, if it could be located.
+    /// For a damaged record, this covers all the bytes skipped over.
+    pub location: Option<RecordLocation>,
+    /// Which index the record was left out of
+    pub skipped_from: SkippedFrom,
+    /// Why the record was skipped
+    pub error: IndexingError,
+}
+impl fmt::Display for IndexingWarning {
+    fn fmt(&self, message: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problem = match self.skipped_from {
+            SkippedFrom::CdxjIndex => "Could not create cdxj record",
+            SkippedFrom::PageIndex => "Could not create page record",
+            SkippedFrom::RevisitHeader => "Could not read revisit header",
+        };
+        write!(message, "{problem} for warc record {}", self.record_number)?;
+        if let Some(record_id) = &self.record_id {
+            write!(message, " with id {record_id}")?;
+        }
+        write!(message, " in {}", self.warc_file_name)?;
+        if let Some(location) = self.location {
+            write!(
+                message,
+                " at bytes {} to {}",
+                location.offset,
+                location.offset + location.length
+            )?;
+        }
+        return write!(message, ": {}", self.error);
+    }
+}
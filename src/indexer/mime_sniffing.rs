@@ -0,0 +1,210 @@
+//! Guesses the media type of a payload from its first few bytes.
+//!
+//! This follows the [WHATWG MIME sniffing](https://mimesniff.spec.whatwg.org/#identifying-a-resource-with-an-unknown-mime-type)
+//! rules for a resource with an unknown media type, allowing scriptable
+//! types such as HTML, with one addition: a payload which is all valid
+//! json is `application/json`, rather than `text/plain`.
+
+/// Only this many bytes at the start of the payload are checked.
+pub const RESOURCE_HEADER_LENGTH: usize = 1445;
+
+/// Whitespace which can come before an HTML or XML tag.
+const WHITESPACE_BYTES: &[u8] = b"\t\n\x0c\r ";
+
+/// HTML tags which identify a payload as HTML, if they are followed
+/// by a space or `>`. These are matched without case.
+const HTML_TAGS: &[&[u8]] = &[
+    b"<!DOCTYPE HTML",
+    b"<HTML",
+    b"<HEAD",
+    b"<SCRIPT",
+    b"<IFRAME",
+    b"<H1",
+    b"<DIV",
+    b"<FONT",
+    b"<TABLE",
+    b"<A",
+    b"<STYLE",
+    b"<TITLE",
+    b"<B",
+    b"<BODY",
+    b"<BR",
+    b"<P",
+];
+
+/// Patterns for the start of the payload, with a mask where `0x00`
+/// ignores the byte in the payload, and the media type they identify.
+const PATTERNS: &[(&[u8], &[u8], &str)] = &[
+    (b"%PDF-", b"\xff\xff\xff\xff\xff", "application/pdf"),
+    (
+        b"%!PS-Adobe-",
+        b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff",
+        "application/postscript",
+    ),
+    // byte order marks
+    (b"\xfe\xff", b"\xff\xff", "text/plain"),
+    (b"\xff\xfe", b"\xff\xff", "text/plain"),
+    (b"\xef\xbb\xbf", b"\xff\xff\xff", "text/plain"),
+    // images
+    (b"\x00\x00\x01\x00", b"\xff\xff\xff\xff", "image/x-icon"),
+    (b"\x00\x00\x02\x00", b"\xff\xff\xff\xff", "image/x-icon"),
+    (b"BM", b"\xff\xff", "image/bmp"),
+    (b"GIF87a", b"\xff\xff\xff\xff\xff\xff", "image/gif"),
+    (b"GIF89a", b"\xff\xff\xff\xff\xff\xff", "image/gif"),
+    (
+        b"RIFF\x00\x00\x00\x00WEBPVP",
+        b"\xff\xff\xff\xff\x00\x00\x00\x00\xff\xff\xff\xff\xff\xff",
+        "image/webp",
+    ),
+    (
+        b"\x89PNG\r\n\x1a\n",
+        b"\xff\xff\xff\xff\xff\xff\xff\xff",
+        "image/png",
+    ),
+    (b"\xff\xd8\xff", b"\xff\xff\xff", "image/jpeg"),
+    // audio and video
+    (
+        b"FORM\x00\x00\x00\x00AIFF",
+        b"\xff\xff\xff\xff\x00\x00\x00\x00\xff\xff\xff\xff",
+        "audio/aiff",
+    ),
+    (b"ID3", b"\xff\xff\xff", "audio/mpeg"),
+    (b"OggS\x00", b"\xff\xff\xff\xff\xff", "application/ogg"),
+    (
+        b"MThd\x00\x00\x00\x06",
+        b"\xff\xff\xff\xff\xff\xff\xff\xff",
+        "audio/midi",
+    ),
+    (
+        b"RIFF\x00\x00\x00\x00AVI ",
+        b"\xff\xff\xff\xff\x00\x00\x00\x00\xff\xff\xff\xff",
+        "video/avi",
+    ),
+    (
+        b"RIFF\x00\x00\x00\x00WAVE",
+        b"\xff\xff\xff\xff\x00\x00\x00\x00\xff\xff\xff\xff",
+        "audio/wave",
+    ),
+    // archives
+    (b"\x1f\x8b\x08", b"\xff\xff\xff", "application/x-gzip"),
+    (b"PK\x03\x04", b"\xff\xff\xff\xff", "application/zip"),
+    (
+        b"Rar!\x1a\x07\x00",
+        b"\xff\xff\xff\xff\xff\xff\xff",
+        "application/x-rar-compressed",
+    ),
+];
+
+/// # Sniff media type
+///
+/// Returns the media type identified from the start of the payload.
+/// If nothing matches, this is `text/plain` unless the payload contains
+/// binary bytes, in which case it is `application/octet-stream`.
+#[must_use]
+pub fn sniff_mime(payload: &[u8]) -> &'static str {
+    let resource_header = payload.get(..RESOURCE_HEADER_LENGTH).unwrap_or(payload);
+
+    // tags can come after whitespace
+    let tag_start = resource_header
+        .iter()
+        .position(|byte| return !WHITESPACE_BYTES.contains(byte))
+        .map_or(&[][..], |position| return &resource_header[position..]);
+    if HTML_TAGS.iter().any(|html_tag| {
+        return tag_start.len() > html_tag.len()
+            && tag_start[..html_tag.len()].eq_ignore_ascii_case(html_tag)
+            && matches!(tag_start[html_tag.len()], b' ' | b'>');
+    }) || tag_start.starts_with(b"<!--")
+    {
+        return "text/html";
+    }
+    if tag_start.starts_with(b"<?xml") {
+        return "text/xml";
+    }
+
+    for (pattern, mask, mime) in PATTERNS {
+        if resource_header.len() >= pattern.len()
+            && resource_header
+                .iter()
+                .zip(pattern.iter().zip(mask.iter()))
+                .all(|(byte, (pattern_byte, mask_byte))| {
+                    return byte & mask_byte == *pattern_byte;
+                })
+        {
+            return mime;
+        }
+    }
+    if is_mp4(resource_header) {
+        return "video/mp4";
+    }
+    if resource_header.starts_with(b"\x1a\x45\xdf\xa3")
+        && resource_header
+            .get(4..38)
+            .is_some_and(|bytes| return bytes.windows(4).any(|window| return window == b"webm"))
+    {
+        return "video/webm";
+    }
+
+    if matches!(tag_start.first(), Some(b'{' | b'['))
+        && serde_json::from_slice::<serde::de::IgnoredAny>(payload).is_ok()
+    {
+        return "application/json";
+    }
+    if is_binary(resource_header) {
+        return "application/octet-stream";
+    }
+    return "text/plain";
+}
+
+/// # Check for binary payload
+///
+/// Returns `true` if the start of the payload contains any control
+/// characters which are not used in text, such as `NUL`.
+#[must_use]
+pub fn is_binary(payload: &[u8]) -> bool {
+    let resource_header = payload.get(..RESOURCE_HEADER_LENGTH).unwrap_or(payload);
+    return resource_header.iter().any(|byte| {
+        return matches!(byte, 0x00..=0x08 | 0x0b | 0x0e..=0x1a | 0x1c..=0x1f);
+    });
+}
+
+/// Check for an `ftyp` box with an `mp4` brand at the start of the payload.
+fn is_mp4(resource_header: &[u8]) -> bool {
+    let Some(box_size) = resource_header.get(..4).map(|size_bytes| {
+        return u32::from_be_bytes([size_bytes[0], size_bytes[1], size_bytes[2], size_bytes[3]])
+            as usize;
+    }) else {
+        return false;
+    };
+    if box_size < 12 || box_size % 4 != 0 || resource_header.len() < box_size {
+        return false;
+    }
+    if resource_header.get(4..8) != Some(b"ftyp") {
+        return false;
+    }
+    // the major brand, then the compatible brands after the minor version
+    return resource_header[8..11] == *b"mp4"
+        || resource_header
+            .get(16..box_size)
+            .unwrap_or_default()
+            .chunks_exact(4)
+            .any(|brand| return brand.starts_with(b"mp4"));
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn sniff_common_types() {
+        assert_eq!(sniff_mime(b"\n  <!doctype html>\n<html>"), "text/html");
+        assert_eq!(sniff_mime(b"%PDF-1.7\n"), "application/pdf");
+        assert_eq!(sniff_mime(b"\x89PNG\r\n\x1a\n\x00\x00"), "image/png");
+        assert_eq!(sniff_mime(b"{\"example\": [1, 2]}"), "application/json");
+        assert_eq!(sniff_mime(b"just some text"), "text/plain");
+        assert_eq!(
+            sniff_mime(b"\x00\x01\x02binary"),
+            "application/octet-stream"
+        );
+    }
+}
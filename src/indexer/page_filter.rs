@@ -0,0 +1,154 @@
+use crate::indexer::HttpResponseHead;
+use std::{fmt, ops::RangeInclusive, sync::Arc};
+use warc::{BufferedBody, Record};
+
+/// A function which makes the final decision on whether a record is a
+/// page, given the record and its parsed HTTP response.
+pub type PagePredicate = dyn Fn(&Record<BufferedBody>, &HttpResponseHead) -> bool + Send + Sync;
+
+/// Rules for which records are listed as pages in the pages.jsonl file.
+///
+/// The WACZ spec does not say what a page is, so each collection can
+/// decide. A record is a page if it is a `response`, `revisit` or
+/// `resource` record which passes every rule:
+///
+/// * its media type is one of `mime_types`
+/// * its HTTP status code is in one of `status_ranges`
+/// * its url matches one of `include_urls`, if there are any
+/// * its url matches none of `exclude_urls`
+/// * the `predicate` returns `true`, if there is one
+///
+/// A revisit record has no media type of its own, so the media type of
+/// the original, from the HTTP headers stored in the revisit, is checked
+/// instead.
+///
+/// Url patterns are matched against the whole url, where `*` matches
+/// any run of characters, for example `https://example.com/blog/*`.
+///
+/// By default, pages are `text/html`, `application/xhtml+xml`, or
+/// `text/plain` records with a 200 OK response.
+#[derive(Clone)]
+pub struct PageFilter {
+    /// Media types which can be pages, in lowercase
+    pub mime_types: Vec<String>,
+    /// Ranges of HTTP status codes which can be pages
+    pub status_ranges: Vec<RangeInclusive<u16>>,
+    /// If not empty, only urls matching one of these patterns are pages
+    pub include_urls: Vec<String>,
+    /// Urls matching any of these patterns are never pages
+    pub exclude_urls: Vec<String>,
+    /// Called for each record which passes the other rules
+    pub predicate: Option<Arc<PagePredicate>>,
+}
+impl PageFilter {
+    /// Returns `true` if the media type can be a page.
+    #[must_use]
+    pub fn allows_mime(&self, mime: &str) -> bool {
+        return self
+            .mime_types
+            .iter()
+            .any(|mime_type| return mime_type.eq_ignore_ascii_case(mime));
+    }
+    /// Returns `true` if the HTTP status code can be a page.
+    #[must_use]
+    pub fn allows_status(&self, status: u16) -> bool {
+        return self
+            .status_ranges
+            .iter()
+            .any(|status_range| return status_range.contains(&status));
+    }
+    /// Returns `true` if the url is included and not excluded.
+    #[must_use]
+    pub fn allows_url(&self, url: &str) -> bool {
+        return (self.include_urls.is_empty()
+            || self
+                .include_urls
+                .iter()
+                .any(|pattern| return matches_pattern(pattern, url)))
+            && !self
+                .exclude_urls
+                .iter()
+                .any(|pattern| return matches_pattern(pattern, url));
+    }
+    /// Returns `true` if there is no predicate, or it accepts the record.
+    #[must_use]
+    pub fn allows_record(
+        &self,
+        record: &Record<BufferedBody>,
+        response_head: &HttpResponseHead,
+    ) -> bool {
+        return self
+            .predicate
+            .as_ref()
+            .is_none_or(|predicate| return predicate(record, response_head));
+    }
+}
+impl Default for PageFilter {
+    fn default() -> Self {
+        return Self {
+            mime_types: ["text/html", "application/xhtml+xml", "text/plain"]
+                .map(str::to_owned)
+                .to_vec(),
+            status_ranges: vec![200..=200],
+            include_urls: Vec::new(),
+            exclude_urls: Vec::new(),
+            predicate: None,
+        };
+    }
+}
+impl fmt::Debug for PageFilter {
+    fn fmt(&self, message: &mut fmt::Formatter) -> fmt::Result {
+        return message
+            .debug_struct("PageFilter")
+            .field("mime_types", &self.mime_types)
+            .field("status_ranges", &self.status_ranges)
+            .field("include_urls", &self.include_urls)
+            .field("exclude_urls", &self.exclude_urls)
+            .field("predicate", &self.predicate.is_some())
+            .finish();
+    }
+}
+
+/// Match a url against a pattern where `*` matches any run of characters.
+fn matches_pattern(pattern: &str, url: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // the first part has to be at the start of the url
+    let Some(remaining_url) = url.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let mut remaining_url = remaining_url;
+    let mut parts = parts.peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            // and the last part has to be at the end
+            return remaining_url.ends_with(part);
+        }
+        match remaining_url.find(part) {
+            Some(position) => remaining_url = &remaining_url[position + part.len()..],
+            None => return false,
+        }
+    }
+    // there was no `*`, so the pattern is the whole url
+    return remaining_url.is_empty();
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn url_patterns() {
+        let page_filter = PageFilter {
+            include_urls: vec!["https://example.com/*".to_owned()],
+            exclude_urls: vec!["*/feed/*".to_owned(), "*.css".to_owned()],
+            ..PageFilter::default()
+        };
+
+        assert!(page_filter.allows_url("https://example.com/"));
+        assert!(page_filter.allows_url("https://example.com/blog/post"));
+        assert!(!page_filter.allows_url("https://example.org/"));
+        assert!(!page_filter.allows_url("https://example.com/blog/feed/"));
+        assert!(!page_filter.allows_url("https://example.com/style.css"));
+    }
+}
@@ -1,72 +1,162 @@
 use crate::indexer::{
-    RecordContentType, RecordStatus, RecordTimestamp, RecordUrl, indexing_errors::IndexingError,
+    CDXJIndexRecord, HttpResponseHead, PageFilter, RecordContentType, RecordRevisit, RecordStatus,
+    RecordTimestamp, RecordUrl,
+    indexing_errors::IndexingError,
+    page_title::{TITLE_SEARCH_LENGTH, page_title},
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use warc::{BufferedBody, Record, RecordType};
 
 /// A page which would make up a line in a pages.jsonl file.
-#[derive(Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct PageRecord {
     /// The date and time when the web archive snapshot was created
     #[serde(rename = "ts")]
     pub timestamp: RecordTimestamp,
     /// The URL that was archived
     pub url: RecordUrl,
+    /// The title of the page, if it is an HTML page with a `<title>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
 }
 impl PageRecord {
     /// # Create page record
     ///
-    /// Takes a `Record<BufferedBody>` and extracts the timestamp
-    /// and url for the pages.jsonl file. This will only produce page
-    /// records for resources with a 200 OK response and a media type
-    /// of either:
+    /// Takes a `Record<BufferedBody>` and its parsed [HTTP response](HttpResponseHead),
+    /// and extracts the timestamp, url and title for the pages.jsonl file.
+    /// This will only produce page records for resources with a 200 OK
+    /// response and a media type of either:
     ///
     /// * `text/html`
     /// * `application/xhtml+xml`
     /// * `text/plain`
     ///
+    /// For a revisit, this is the media type of the original, from the
+    /// HTTP headers stored in the revisit, see [`RecordRevisit`].
+    ///
+    /// The title is read from the first 64 KiB of the decoded payload of
+    /// HTML pages, see [`HttpResponseHead::decoded_payload`].
+    ///
     /// # Errors
     ///
     /// Returns an `UnindexableRecordType` error if the record is not
     /// a Warc `response`, `revisit`, or `resource`. Otherwise, returns
     /// corresponding errors for url, timestamp mime, or status fields.
-    pub fn new(record: &Record<BufferedBody>) -> Result<Self, IndexingError> {
-        let mime = RecordContentType::new(record)?;
-        let status = RecordStatus::new(record)?;
+    pub fn new(
+        record: &Record<BufferedBody>,
+        response_head: &HttpResponseHead,
+    ) -> Result<Self, IndexingError> {
+        return Self::new_with_filter(record, response_head, &PageFilter::default());
+    }
+    /// # Create page record with filter
+    ///
+    /// The same as [`PageRecord::new`], but with a [`PageFilter`]
+    /// deciding which records are pages.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `UnindexableRecordType` error if the record is not
+    /// a page, see [`PageRecord::new`] for the other errors.
+    pub fn new_with_filter(
+        record: &Record<BufferedBody>,
+        response_head: &HttpResponseHead,
+        page_filter: &PageFilter,
+    ) -> Result<Self, IndexingError> {
+        // every revisit has the `warc/revisit` media type, so
+        // the media type of the original is checked instead
+        let mime = if record.warc_type() == &RecordType::Revisit {
+            RecordRevisit::new(record, Some(response_head)).original_mime
+        } else {
+            Some(RecordContentType::new(record, response_head)?)
+        };
+        return Self::with_mime(record, response_head, mime.as_ref(), page_filter);
+    }
+    /// # Create page record from CDX(J) record
+    ///
+    /// The same as [`PageRecord::new_with_filter`], but takes the media
+    /// type from the [CDX(J) record](CDXJIndexRecord) already made for
+    /// this WARC record, or from its [revisit](RecordRevisit) details,
+    /// rather than working it out again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `UnindexableRecordType` error if the record is not
+    /// a page, see [`PageRecord::new`] for the other errors.
+    pub fn from_cdxj_record(
+        record: &Record<BufferedBody>,
+        response_head: &HttpResponseHead,
+        cdxj_record: &CDXJIndexRecord,
+        page_filter: &PageFilter,
+    ) -> Result<Self, IndexingError> {
+        let mime = match &cdxj_record.revisit {
+            Some(revisit) => revisit.original_mime.as_ref(),
+            None => cdxj_record.mime.as_ref(),
+        };
+        return Self::with_mime(record, response_head, mime, page_filter);
+    }
+    /// Create a page record from a WARC record with this media type,
+    /// which is `None` if it is not known, such as for a revisit
+    /// without the HTTP headers of the original.
+    fn with_mime(
+        record: &Record<BufferedBody>,
+        response_head: &HttpResponseHead,
+        mime: Option<&RecordContentType>,
+        page_filter: &PageFilter,
+    ) -> Result<Self, IndexingError> {
+        let Some(mime) = mime else {
+            return Err(IndexingError::UnindexableRecordType(
+                record.warc_type().clone(),
+            ));
+        };
+        let status = RecordStatus::new(response_head)?;
 
         // first check whether the record is either a response, revisit,
-        // resource, or metadata and check whether the record mime type
-        // refers to a web page
+        // or resource and check whether the record mime type and
+        // status refer to a web page
         if [
             RecordType::Response,
             RecordType::Revisit,
             RecordType::Resource,
         ]
         .contains(record.warc_type())
-            && ["text/html", "application/xhtml+xml", "text/plain"]
-                .contains(&mime.to_string().as_str())
-            && status == RecordStatus(200)
+            && page_filter.allows_mime(&mime.mime)
+            && page_filter.allows_status(status.0)
         {
-            return Ok(Self {
-                timestamp: RecordTimestamp::new(record)?,
-                url: RecordUrl::new(record)?,
-            });
-        } else {
-            // if the record is not one of the types we want,
-            // return an error
-            // change this to a generic indexing error?
-            return Err(IndexingError::UnindexableRecordType(
-                record.warc_type().clone(),
-            ));
+            let url = RecordUrl::new(record)?;
+            if page_filter.allows_url(&url.to_string())
+                && page_filter.allows_record(record, response_head)
+            {
+                let title = if ["text/html", "application/xhtml+xml"].contains(&mime.mime.as_str())
+                {
+                    response_head
+                        .decoded_payload(record.body(), TITLE_SEARCH_LENGTH)
+                        .and_then(|payload| return page_title(&payload, mime.charset.as_deref()))
+                } else {
+                    None
+                };
+                return Ok(Self {
+                    timestamp: RecordTimestamp::new(record)?,
+                    url,
+                    title,
+                });
+            }
         }
+        // if the record is not one of the types we want,
+        // return an error
+        // change this to a generic indexing error?
+        return Err(IndexingError::UnindexableRecordType(
+            record.warc_type().clone(),
+        ));
     }
 }
 /// Display the record to json.
 impl fmt::Display for PageRecord {
     fn fmt(&self, message: &mut fmt::Formatter) -> fmt::Result {
-        let pages_json_string = serde_json::to_string(self).unwrap();
-        return writeln!(message, "{pages_json_string}");
+        match serde_json::to_string(self) {
+            Ok(pages_json_string) => return writeln!(message, "{pages_json_string}"),
+            Err(_) => return Err(fmt::Error),
+        }
     }
 }
 
@@ -89,12 +179,65 @@ mod tests {
             .unwrap();
         let record = headers.add_body("HTTP/1.1 200\ncontent-type: text/html\n");
 
-        let generated_page_record = PageRecord::new(&record).unwrap().to_string();
+        let response_head = HttpResponseHead::parse(record.body()).unwrap();
+        let generated_page_record = PageRecord::new(&record, &response_head)
+            .unwrap()
+            .to_string();
         let example_page_record =
             format!("{{\"ts\":\"2025-08-06T13:37:28Z\",\"url\":\"{target_url}\"}}\n");
 
         assert_eq!(generated_page_record, example_page_record);
     }
 
-    // todo: test the different conditions, eg. a resource with a different content type
+    #[test]
+    fn page_filter_with_redirects() {
+        let mut headers = Record::<BufferedBody>::new();
+        headers.set_warc_type(RecordType::Response);
+        headers
+            .set_header(WarcHeader::Date, "2025-08-06T14:37:28+01:00")
+            .unwrap();
+        headers
+            .set_header(WarcHeader::TargetURI, "https://example.com/old")
+            .unwrap();
+        let record = headers.add_body("HTTP/1.1 301\ncontent-type: text/html\n");
+        let response_head = HttpResponseHead::parse(record.body()).unwrap();
+
+        assert!(PageRecord::new(&record, &response_head).is_err());
+
+        let page_filter = PageFilter {
+            status_ranges: vec![200..=200, 300..=399],
+            ..PageFilter::default()
+        };
+        assert!(PageRecord::new_with_filter(&record, &response_head, &page_filter).is_ok());
+
+        let page_filter = PageFilter {
+            predicate: Some(std::sync::Arc::new(|_, response_head| {
+                return response_head.header("location").is_some();
+            })),
+            ..page_filter
+        };
+        assert!(PageRecord::new_with_filter(&record, &response_head, &page_filter).is_err());
+    }
+
+    #[test]
+    fn revisit_page_record() {
+        let mut headers = Record::<BufferedBody>::new();
+        headers.set_warc_type(RecordType::Revisit);
+        headers
+            .set_header(WarcHeader::Date, "2025-08-06T14:37:28+01:00")
+            .unwrap();
+        headers
+            .set_header(WarcHeader::TargetURI, "https://example.com/")
+            .unwrap();
+        let record = headers.add_body("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n");
+        let response_head = HttpResponseHead::parse(record.body()).unwrap();
+
+        assert!(PageRecord::new(&record, &response_head).is_ok());
+
+        let page_filter = PageFilter {
+            mime_types: vec!["text/plain".to_owned()],
+            ..PageFilter::default()
+        };
+        assert!(PageRecord::new_with_filter(&record, &response_head, &page_filter).is_err());
+    }
 }
@@ -0,0 +1,169 @@
+//! Reads the title of an HTML page from its payload.
+
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE, WINDOWS_1252};
+
+/// Only this many bytes at the start of the page are searched for
+/// a `<meta>` tag declaring the charset, as browsers do.
+const META_CHARSET_LENGTH: usize = 1024;
+
+/// Only this many bytes at the start of the page are decoded and
+/// searched for a title, which is nearly always in the `<head>`.
+pub const TITLE_SEARCH_LENGTH: usize = 64 * 1024;
+
+/// # Read page title
+///
+/// Decodes the HTML payload to text, and returns the text of the
+/// first `<title>` element, with any character references decoded and
+/// runs of whitespace collapsed. Returns `None` if there is no title,
+/// or it is empty.
+///
+/// The payload is decoded with the charset from a byte order mark if
+/// there is one, then the `charset` from the `Content-Type` header,
+/// then a `<meta>` tag near the start of the page. Without any of these,
+/// it is decoded as UTF-8 if it is valid, or otherwise as windows-1252.
+#[must_use]
+pub fn page_title(payload: &[u8], charset: Option<&str>) -> Option<String> {
+    let encoding = Encoding::for_bom(payload)
+        .map(|(encoding, _)| return encoding)
+        .or_else(|| {
+            return charset.and_then(|charset| return Encoding::for_label(charset.as_bytes()));
+        })
+        .or_else(|| return meta_charset(payload))
+        .unwrap_or_else(|| {
+            match str::from_utf8(payload) {
+                Ok(_) => return UTF_8,
+                // the payload may be cut off part way through a character
+                Err(utf8_error) if utf8_error.error_len().is_none() => return UTF_8,
+                Err(_) => return WINDOWS_1252,
+            }
+        });
+    let (page, _, _) = encoding.decode(payload);
+
+    // lowercasing ascii keeps every character at the same position
+    let lowercase_page = page.to_ascii_lowercase();
+    let mut search_from = 0;
+    let title_start = loop {
+        let tag_start = search_from + lowercase_page[search_from..].find("<title")?;
+        let after_name = tag_start + "<title".len();
+        // make sure this is not another tag, such as <titles>
+        if lowercase_page[after_name..]
+            .starts_with(|character: char| return character == '>' || character.is_whitespace())
+        {
+            break after_name + lowercase_page[after_name..].find('>')? + 1;
+        }
+        search_from = after_name;
+    };
+    let title_end = title_start + lowercase_page[title_start..].find("</title")?;
+
+    let title = decode_character_references(&page[title_start..title_end])
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ");
+    if title.is_empty() {
+        return None;
+    }
+    return Some(title);
+}
+
+/// Find the charset declared in a `<meta>` tag, such as
+/// `<meta charset="utf-8">`, or `<meta http-equiv="Content-Type"
+/// content="text/html; charset=utf-8">`.
+fn meta_charset(payload: &[u8]) -> Option<&'static Encoding> {
+    let page_start = payload.get(..META_CHARSET_LENGTH).unwrap_or(payload);
+    let page_start = String::from_utf8_lossy(page_start).to_ascii_lowercase();
+    for meta_tag in page_start.split("<meta").skip(1) {
+        let meta_tag = meta_tag.split('>').next().unwrap_or_default();
+        let Some((_, charset)) = meta_tag.split_once("charset") else {
+            continue;
+        };
+        let Some(charset) = charset.trim_start().strip_prefix('=') else {
+            continue;
+        };
+        let charset = charset
+            .trim_start_matches(|character: char| {
+                return character.is_whitespace() || character == '"' || character == '\'';
+            })
+            .split(|character: char| {
+                return character.is_whitespace() || ['"', '\'', ';', '/'].contains(&character);
+            })
+            .next()
+            .unwrap_or_default();
+        if let Some(encoding) = Encoding::for_label(charset.as_bytes()) {
+            // a page which can declare its charset in ascii cannot be UTF-16
+            if encoding == UTF_16BE || encoding == UTF_16LE {
+                return Some(UTF_8);
+            }
+            return Some(encoding);
+        }
+    }
+    return None;
+}
+
+/// Decode the character references most often found in titles, such
+/// as `&amp;` and `&#8211;`. Any others are left as they are.
+fn decode_character_references(text: &str) -> String {
+    let mut decoded_text = String::with_capacity(text.len());
+    let mut remaining_text = text;
+    while let Some(reference_start) = remaining_text.find('&') {
+        decoded_text.push_str(&remaining_text[..reference_start]);
+        remaining_text = &remaining_text[reference_start..];
+        let decoded_reference = remaining_text.find(';').and_then(|reference_end| {
+            let reference = &remaining_text[1..reference_end];
+            let character = match reference {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => reference
+                    .strip_prefix("#x")
+                    .or_else(|| return reference.strip_prefix("#X"))
+                    .map_or_else(
+                        || {
+                            return reference
+                                .strip_prefix('#')
+                                .and_then(|number| return number.parse::<u32>().ok());
+                        },
+                        |hex_number| return u32::from_str_radix(hex_number, 16).ok(),
+                    )
+                    .and_then(char::from_u32),
+            };
+            return character.map(|character| return (character, reference_end + 1));
+        });
+        if let Some((character, reference_length)) = decoded_reference {
+            decoded_text.push(character);
+            remaining_text = &remaining_text[reference_length..];
+        } else {
+            decoded_text.push('&');
+            remaining_text = &remaining_text[1..];
+        }
+    }
+    decoded_text.push_str(remaining_text);
+    return decoded_text;
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn title_with_meta_charset() {
+        let payload = b"<!DOCTYPE html>\n<html><head><meta charset=\"windows-1252\">\n<TITLE>\n  Caf\xe9 &amp; bar &#8211;\n  menu </TITLE></head>";
+
+        assert_eq!(
+            page_title(payload, None).as_deref(),
+            Some("Caf\u{e9} & bar \u{2013} menu")
+        );
+        assert_eq!(page_title(b"<html><title></title>", None), None);
+    }
+
+    #[test]
+    fn title_of_cut_off_page() {
+        // the page is cut off part way through the two bytes of `é`
+        let payload = b"<html><title>Caf\xc3\xa9</title><p>Caf\xc3";
+
+        assert_eq!(page_title(payload, None).as_deref(), Some("Caf\u{e9}"));
+    }
+}
@@ -0,0 +1,327 @@
+use crate::indexer::{
+    CDXJIndex, CDXJIndexRecord, IndexingError, RecordContentType, RecordTimestamp, RecordUrl,
+};
+use std::str::FromStr as _;
+
+/// How the url in a [query](CDXJQuery) is matched against the
+/// searchable urls in the index.
+///
+/// This follows the `matchType` parameter of the [pywb CDX server](https://pywb.readthedocs.io/en/latest/manual/cdxserver_api.html#matchtype).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatchType {
+    /// Only records for this exact url.
+    #[default]
+    Exact,
+    /// Records for any url starting with this url.
+    Prefix,
+    /// Records for any url on the same host.
+    Host,
+    /// Records for any url on the same host or any of its subdomains.
+    Domain,
+}
+
+/// A search over a [CDX(J) index](CDXJIndex).
+///
+/// Start with the url to look up, and then narrow down the results:
+///
+/// ```
+/// use wacksy::indexer::{CDXJQuery, MatchType};
+///
+/// let query = CDXJQuery::new("https://example.com/")
+///     .match_type(MatchType::Host)
+///     .from("2025")
+///     .to("202504")
+///     .status(200)
+///     .mime("text/html");
+/// ```
+///
+/// Timestamps can be given to any precision, from a year such as `2025`
+/// up to a full 14-digit timestamp. As in pywb, `from` is padded out to
+/// the start of the period and `to` to the end of it, so both bounds
+/// are inclusive.
+#[derive(Clone, Debug, Default)]
+pub struct CDXJQuery {
+    url: String,
+    match_type: MatchType,
+    from: Option<String>,
+    to: Option<String>,
+    closest: Option<String>,
+    status: Option<u16>,
+    mime: Option<String>,
+    limit: Option<usize>,
+}
+
+impl CDXJQuery {
+    /// Search for records matching `url`, this can be given without
+    /// a scheme, in which case `http://` is assumed.
+    #[must_use]
+    pub fn new(url: &str) -> Self {
+        return Self {
+            url: url.to_owned(),
+            ..Self::default()
+        };
+    }
+    /// Set how the url is matched, by default only the exact url is matched.
+    #[must_use]
+    pub const fn match_type(mut self, match_type: MatchType) -> Self {
+        self.match_type = match_type;
+        return self;
+    }
+    /// Only return records captured at or after this timestamp.
+    #[must_use]
+    pub fn from(mut self, timestamp: &str) -> Self {
+        self.from = Some(timestamp.to_owned());
+        return self;
+    }
+    /// Only return records captured at or before this timestamp.
+    #[must_use]
+    pub fn to(mut self, timestamp: &str) -> Self {
+        self.to = Some(timestamp.to_owned());
+        return self;
+    }
+    /// Order the results by how close they were captured to this
+    /// timestamp, nearest first, rather than in index order.
+    #[must_use]
+    pub fn closest(mut self, timestamp: &str) -> Self {
+        self.closest = Some(timestamp.to_owned());
+        return self;
+    }
+    /// Only return records with this HTTP status code.
+    #[must_use]
+    pub const fn status(mut self, status: u16) -> Self {
+        self.status = Some(status);
+        return self;
+    }
+    /// Only return records with this media type. Any parameters such
+    /// as `charset` are ignored, and the comparison is case-insensitive.
+    #[must_use]
+    pub fn mime(mut self, mime: &str) -> Self {
+        self.mime = Some(mime.to_owned());
+        return self;
+    }
+    /// Return at most this many records.
+    #[must_use]
+    pub const fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        return self;
+    }
+}
+
+impl CDXJIndex {
+    /// # Query index
+    ///
+    /// Finds the records matching a [`CDXJQuery`]. The index must be
+    /// [sorted](CDXJIndex::sort), as the matching records are found with
+    /// a binary search on the searchable url. Results are returned in
+    /// index order, unless the query asks for the
+    /// [closest](CDXJQuery::closest) records to a timestamp.
+    ///
+    /// Request records for `POST` and other methods are indexed with a
+    /// media type of `warc/request`. These are left out of the results,
+    /// unless the query asks for this [media type](CDXJQuery::mime).
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RecordUrlError` if the url in the query cannot be
+    /// parsed, or an `IndexParsingError` if any of the timestamps in
+    /// the query are not made up of up to 14 digits.
+    pub fn query(&self, query: &CDXJQuery) -> Result<Vec<&CDXJIndexRecord>, IndexingError> {
+        let url_key = search_key(&query.url, query.match_type)?;
+        let from = match &query.from {
+            Some(timestamp) => Some(pad_timestamp(timestamp, "00000000000000")?),
+            None => None,
+        };
+        let to = match &query.to {
+            Some(timestamp) => Some(pad_timestamp(timestamp, "99999999999999")?),
+            None => None,
+        };
+        let closest = match &query.closest {
+            Some(timestamp) => Some(RecordTimestamp::from_str(&pad_timestamp(
+                timestamp,
+                "00000101000000",
+            )?)?),
+            None => None,
+        };
+
+        // the index is sorted by searchable url, so all the matching
+        // records are in one run starting from the first possible match
+        let range_start = self
+            .0
+            .partition_point(|record| return record.searchable_url.as_str() < url_key.as_str());
+        let mut results: Vec<&CDXJIndexRecord> = self.0[range_start..]
+            .iter()
+            .take_while(|record| return record.searchable_url.starts_with(url_key.as_str()))
+            .filter(|record| {
+                return url_matches(&record.searchable_url, &url_key, query.match_type);
+            })
+            .filter(|record| {
+                let timestamp = record.timestamp.to_string();
+                return from.as_ref().is_none_or(|from| return &timestamp >= from)
+                    && to.as_ref().is_none_or(|to| return &timestamp <= to);
+            })
+            .filter(|record| {
+                return query.status.is_none_or(|status| {
+                    return record
+                        .status
+                        .as_ref()
+                        .is_some_and(|record_status| return record_status.0 == status);
+                });
+            })
+            .filter(|record| {
+                return query.mime.as_ref().is_none_or(|mime| {
+                    return record.mime.as_ref().is_some_and(|record_mime| {
+                        return mime_matches(&record_mime.to_string(), mime);
+                    });
+                });
+            })
+            // a request has the same searchable url and timestamp as its
+            // response, and may come first, but there is nothing to replay
+            .filter(|record| {
+                return query.mime.is_some()
+                    || record.mime.as_ref().is_none_or(|record_mime| {
+                        return record_mime.mime != RecordContentType::REQUEST;
+                    });
+            })
+            .collect();
+
+        if let Some(closest) = closest {
+            // a stable sort keeps records which are equally
+            // close in the same order as in the index
+            results.sort_by_key(|record| return record.timestamp.seconds_between(&closest));
+        }
+        if let Some(limit) = query.limit {
+            results.truncate(limit);
+        }
+        return Ok(results);
+    }
+}
+
+/// Work out the start of the run of searchable urls which could match
+/// the query. For host and domain queries this is just the host part
+/// of the searchable url, such as `com,example)/` or `com,example`.
+fn search_key(url: &str, match_type: MatchType) -> Result<String, IndexingError> {
+    let url = if url.contains("://") || url.starts_with("urn:") {
+        RecordUrl::from_str(url)?
+    } else {
+        RecordUrl::from_str(&format!("http://{url}"))?
+    };
+    let searchable_url = url.as_searchable_string()?;
+    match match_type {
+        MatchType::Exact => return Ok(searchable_url),
+        MatchType::Prefix => {
+            // a trailing slash on the url is dropped from the searchable
+            // url, so put it back to avoid matching sibling paths
+            if url.to_string().ends_with('/') && !searchable_url.ends_with('/') {
+                return Ok(format!("{searchable_url}/"));
+            } else {
+                return Ok(searchable_url);
+            }
+        }
+        MatchType::Host | MatchType::Domain => {
+            let host = searchable_url
+                .split_once(')')
+                .map_or(searchable_url.as_str(), |(host, _)| return host);
+            if match_type == MatchType::Host {
+                return Ok(format!("{host})/"));
+            } else {
+                return Ok(host.to_owned());
+            }
+        }
+    }
+}
+
+/// Check a searchable url from the index against the search key. Every
+/// url in the run already starts with the key, so only exact and domain
+/// matches need checking further.
+fn url_matches(searchable_url: &str, url_key: &str, match_type: MatchType) -> bool {
+    match match_type {
+        MatchType::Exact => return searchable_url == url_key,
+        MatchType::Prefix | MatchType::Host => return true,
+        // a subdomain adds another comma-separated part to the host,
+        // anything else is a different domain such as `com,examples`
+        MatchType::Domain => {
+            return searchable_url[url_key.len()..].starts_with([')', ',']);
+        }
+    }
+}
+
+/// Compare media types, ignoring any parameters and case.
+fn mime_matches(record_mime: &str, mime: &str) -> bool {
+    let media_type = record_mime
+        .split_once(';')
+        .map_or(record_mime, |(media_type, _)| return media_type);
+    return media_type.trim().eq_ignore_ascii_case(mime.trim());
+}
+
+/// Pad a timestamp of up to 14 digits out to the full
+/// length, using the end of the given padding.
+fn pad_timestamp(timestamp: &str, padding: &str) -> Result<String, IndexingError> {
+    if timestamp.len() > 14 || !timestamp.bytes().all(|byte| return byte.is_ascii_digit()) {
+        return Err(IndexingError::IndexParsingError(format!(
+            "expected a timestamp of up to 14 digits, found {timestamp}"
+        )));
+    }
+    return Ok(format!("{timestamp}{}", &padding[timestamp.len()..]));
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn query_cdxj_index() {
+        let cdxj_index: CDXJIndex = "com,example)/ 20250423121042 {\"url\":\"https://example.com/\",\"mime\":\"text/html\",\"offset\":0,\"length\":1,\"status\":200,\"filename\":\"example.warc.gz\"}
+com,example)/ 20250501000000 {\"url\":\"https://example.com/\",\"mime\":\"text/html; charset=utf-8\",\"offset\":1,\"length\":1,\"status\":200,\"filename\":\"example.warc.gz\"}
+com,example)/favicon.ico 20250423121042 {\"url\":\"https://example.com/favicon.ico\",\"mime\":\"text/html\",\"offset\":2,\"length\":1,\"status\":404,\"filename\":\"example.warc.gz\"}
+com,example,blog)/ 20250423121042 {\"url\":\"https://blog.example.com/\",\"mime\":\"text/html\",\"offset\":3,\"length\":1,\"status\":200,\"filename\":\"example.warc.gz\"}
+com,examples)/ 20250423121042 {\"url\":\"https://examples.com/\",\"mime\":\"text/html\",\"offset\":4,\"length\":1,\"status\":200,\"filename\":\"example.warc.gz\"}"
+            .parse()
+            .unwrap();
+        let offsets = |query: CDXJQuery| -> Vec<u64> {
+            return cdxj_index
+                .query(&query)
+                .unwrap()
+                .iter()
+                .map(|record| return record.offset)
+                .collect();
+        };
+
+        assert_eq!(offsets(CDXJQuery::new("https://example.com/")), [0, 1]);
+        assert_eq!(
+            offsets(CDXJQuery::new("example.com").match_type(MatchType::Prefix)),
+            [0, 1, 2]
+        );
+        assert_eq!(
+            offsets(CDXJQuery::new("example.com").match_type(MatchType::Host)),
+            [0, 1, 2]
+        );
+        assert_eq!(
+            offsets(CDXJQuery::new("example.com").match_type(MatchType::Domain)),
+            [0, 1, 2, 3]
+        );
+        assert_eq!(offsets(CDXJQuery::new("example.com").from("202505")), [1]);
+        assert_eq!(offsets(CDXJQuery::new("example.com").to("202504")), [0]);
+        assert_eq!(
+            offsets(CDXJQuery::new("example.com").closest("20250430")),
+            [1, 0]
+        );
+        assert_eq!(
+            offsets(CDXJQuery::new("example.com").mime("TEXT/HTML").limit(1)),
+            [0]
+        );
+        assert_eq!(
+            offsets(
+                CDXJQuery::new("example.com")
+                    .match_type(MatchType::Prefix)
+                    .status(404)
+            ),
+            [2]
+        );
+        assert!(
+            cdxj_index
+                .query(&CDXJQuery::new("example.com").from("2025-04"))
+                .is_err()
+        );
+    }
+}
@@ -1,10 +1,32 @@
-use crate::indexer::indexing_errors::IndexingError;
-use std::{fmt, str};
-use warc::{BufferedBody, Record, RecordType};
+use crate::indexer::{HttpResponseHead, indexing_errors::IndexingError, mime_sniffing};
+use std::{borrow::Cow, fmt, str};
+use warc::{BufferedBody, Record, RecordType, WarcHeader};
 
-pub struct RecordContentType(String);
+/// The media type of a record, such as `text/html`.
+///
+/// Any parameters after the media type are dropped, apart from the
+/// charset, which is kept separately, so `text/html; charset=UTF-8`
+/// becomes a media type of `text/html` and a charset of `UTF-8`.
+///
+/// If the `Content-Type` header is missing or unusable, the media type
+/// is [sniffed](mime_sniffing::sniff_mime) from the payload instead, and
+/// both the declared and sniffed media types are kept.
+pub struct RecordContentType {
+    /// The media type, in lowercase
+    pub mime: String,
+    /// The value of the charset parameter, if there is one
+    pub charset: Option<String>,
+    /// The media type from the `Content-Type` header, if there is one
+    pub declared_mime: Option<String>,
+    /// The media type sniffed from the payload, if it was needed
+    pub sniffed_mime: Option<String>,
+}
 
 impl RecordContentType {
+    /// The media type of every revisit record.
+    pub const REVISIT: &'static str = "warc/revisit";
+    /// The media type of every request record.
+    pub const REQUEST: &'static str = "warc/request";
     /// # Parse record content type
     ///
     /// Parses the HTTP content type from the HTTP headers in
@@ -12,61 +34,163 @@ impl RecordContentType {
     /// [content type from the WARC header](https://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/#content-type),
     /// which would ususally be `application/http`.
     ///
-    /// If the WARC record type is `revisit`, in which case the spec
-    /// says to directly return that as the content type.
+    /// If the WARC record type is `revisit`, the spec says to return
+    /// `warc/revisit` as the content type instead. The content type of
+    /// the original is kept in the [`RecordRevisit`](crate::indexer::RecordRevisit).
+    ///
+    /// If there is no content type, or it is one which browsers would
+    /// sniff, such as `unknown/unknown`, the media type is sniffed from
+    /// the payload. A `text/plain` content type on a binary payload,
+    /// which some servers send by default, is sniffed as well. Any
+    /// content or transfer encoding, such as gzip, is undone before the
+    /// payload is sniffed.
     ///
     /// # Errors
     ///
-    /// Returns a `RecordContentTypeError` in case of any problems with
-    /// parsing; this either wraps `httparse::Error`, or a `Utf8Error` when
-    /// parsing the content type to string. Alternatively returns `ValueNotFound`
-    /// if no content type is found in the HTTP headers.
-    pub fn new(record: &Record<BufferedBody>) -> Result<Self, IndexingError> {
+    /// Returns a `RecordContentTypeError` wrapping a `Utf8Error` when
+    /// parsing the content type to string.
+    pub fn new(
+        record: &Record<BufferedBody>,
+        response_head: &HttpResponseHead,
+    ) -> Result<Self, IndexingError> {
         if record.warc_type() == &RecordType::Revisit {
-            return Ok(Self("revisit".to_owned()));
+            return Ok(Self::from(Self::REVISIT.to_owned()));
+        }
+        // the payload is only decoded if it needs checking, and if it
+        // cannot be decoded, the encoded payload is checked instead
+        let payload = || {
+            return response_head
+                .decoded_payload(record.body(), mime_sniffing::RESOURCE_HEADER_LENGTH)
+                .unwrap_or_else(|| {
+                    return Cow::Borrowed(
+                        record
+                            .body()
+                            .get(response_head.body_offset..)
+                            .unwrap_or_default(),
+                    );
+                });
+        };
+        // return the declared content type, unless it needs sniffing
+        let declared = match Self::from_http_headers(response_head) {
+            Ok(declared) if declared.is_undefined() => Some(declared),
+            Ok(declared) if declared.is_default_text() && mime_sniffing::is_binary(&payload()) => {
+                Some(declared)
+            }
+            Ok(declared) => return Ok(declared),
+            Err(IndexingError::ValueNotFound(_)) => None,
+            Err(parsing_error) => return Err(parsing_error),
+        };
+        let sniffed_mime = if declared.as_ref().is_some_and(Self::is_default_text) {
+            "application/octet-stream"
         } else {
-            // create a list of 64 empty headers, if this is not
-            // enough then you'll get a TooManyHeaders error
-            let mut headers = [httparse::EMPTY_HEADER; 64];
-            let mut response = httparse::Response::new(&mut headers);
-            match response.parse(record.body()) {
-                Ok(status) => status,
-                Err(http_parsing_error) => {
+            mime_sniffing::sniff_mime(&payload())
+        };
+        return Ok(Self {
+            mime: sniffed_mime.to_owned(),
+            charset: declared
+                .as_ref()
+                .and_then(|declared| return declared.charset.clone()),
+            declared_mime: declared.map(|declared| return declared.mime),
+            sniffed_mime: Some(sniffed_mime.to_owned()),
+        });
+    }
+    /// # Parse HTTP content type
+    ///
+    /// The same as [`RecordContentType::new`], but always reads the
+    /// content type from the HTTP headers, even for a revisit record.
+    ///
+    /// Header names are matched without case. If there is more than one
+    /// `Content-Type` header, the last one is used, as browsers do.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`RecordContentType::new`].
+    pub fn from_http_headers(response_head: &HttpResponseHead) -> Result<Self, IndexingError> {
+        match response_head.header_values("content-type").last() {
+            Some(content_type) => match str::from_utf8(content_type) {
+                Ok(parsed_content_type) => return Ok(Self::from(parsed_content_type.to_owned())),
+                Err(parsing_error) => {
                     return Err(IndexingError::RecordContentTypeError(
-                        http_parsing_error.to_string(),
+                        parsing_error.to_string(),
                     ));
                 }
-            };
-
-            // loop through the list of headers looking for the content-type
-            let mut content_type: Option<Result<&str, str::Utf8Error>> = None;
-            for header in &headers {
-                if header.name == "content-type" {
-                    content_type = Some(str::from_utf8(header.value));
-                    break;
-                }
+            },
+            None => {
+                return Err(IndexingError::ValueNotFound(
+                    "content type not present in HTTP headers".to_owned(),
+                ));
             }
-            match content_type {
-                Some(some_content_type) => match some_content_type {
-                    Ok(parsed_content_type) => return Ok(Self(parsed_content_type.to_owned())),
-                    Err(parsing_error) => {
-                        return Err(IndexingError::RecordContentTypeError(
-                            parsing_error.to_string(),
-                        ));
-                    }
-                },
-                None => {
-                    return Err(IndexingError::ValueNotFound(
-                        "content type not present in HTTP headers".to_owned(),
-                    ));
-                }
-            };
         }
     }
+    /// # Parse WARC content type
+    ///
+    /// Reads the content type from the WARC header rather than from an
+    /// HTTP response, for `resource` and `metadata` records which hold
+    /// a file, such as the JSON in a `urn:pageinfo` record.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ValueNotFound` error if there is no `Content-Type`
+    /// in the WARC header.
+    pub fn from_warc_header(record: &Record<BufferedBody>) -> Result<Self, IndexingError> {
+        match record.header(WarcHeader::ContentType) {
+            Some(content_type) => return Ok(Self::from(content_type.into_owned())),
+            None => {
+                return Err(IndexingError::ValueNotFound(
+                    "content type not present in the WARC header".to_owned(),
+                ));
+            }
+        }
+    }
+    /// Returns `true` if the media type is one which browsers treat as
+    /// missing, such as `unknown/unknown`, or is not a valid media type.
+    fn is_undefined(&self) -> bool {
+        match self.mime.split_once('/') {
+            Some((mime_type, mime_subtype)) => {
+                return mime_type.is_empty()
+                    || mime_subtype.is_empty()
+                    || ["unknown/unknown", "application/unknown", "*/*"]
+                        .contains(&self.mime.as_str());
+            }
+            None => return true,
+        }
+    }
+    /// Returns `true` if the content type is one of those which Apache
+    /// sends by default, whatever the payload, so it cannot be trusted
+    /// if the payload is binary.
+    fn is_default_text(&self) -> bool {
+        return self.mime == "text/plain"
+            && matches!(
+                self.charset.as_deref(),
+                None | Some("ISO-8859-1" | "iso-8859-1" | "UTF-8")
+            );
+    }
+}
+/// Split a content type such as `text/html; charset=utf-8`
+/// into the media type and charset.
+impl From<String> for RecordContentType {
+    fn from(value: String) -> Self {
+        let mut parameters = value.split(';');
+        let mime = parameters.next().unwrap_or_default().trim().to_lowercase();
+        let charset = parameters.find_map(|parameter| {
+            let (name, charset) = parameter.split_once('=')?;
+            if name.trim().eq_ignore_ascii_case("charset") {
+                return Some(charset.trim().trim_matches('"').to_owned());
+            } else {
+                return None;
+            }
+        });
+        return Self {
+            declared_mime: Some(mime.clone()),
+            mime,
+            charset,
+            sniffed_mime: None,
+        };
+    }
 }
 impl fmt::Display for RecordContentType {
     fn fmt(&self, message: &mut fmt::Formatter) -> fmt::Result {
-        return write!(message, "{}", self.0);
+        return write!(message, "{}", self.mime);
     }
 }
 
@@ -79,10 +203,60 @@ mod tests {
     fn valid_content_type() {
         let headers = Record::<BufferedBody>::new();
         let record = headers.add_body("HTTP/1.1 200\ncontent-type: text/html\n");
+        let response_head = HttpResponseHead::parse(record.body()).unwrap();
 
-        let generated_content_type = RecordContentType::new(&record).unwrap().to_string();
+        let generated_content_type = RecordContentType::new(&record, &response_head)
+            .unwrap()
+            .to_string();
         let example_content_type = "text/html";
 
         assert_eq!(generated_content_type, example_content_type);
     }
+
+    #[test]
+    fn content_type_with_charset() {
+        let headers = Record::<BufferedBody>::new();
+        let record = headers
+            .add_body("HTTP/1.1 200 OK\r\nContent-Type: Text/HTML; Charset=\"UTF-8\"\r\n\r\n");
+
+        let response_head = HttpResponseHead::parse(record.body()).unwrap();
+        let content_type = RecordContentType::new(&record, &response_head).unwrap();
+
+        assert_eq!(content_type.mime, "text/html");
+        assert_eq!(content_type.charset.as_deref(), Some("UTF-8"));
+    }
+
+    #[test]
+    fn sniffed_content_type() {
+        let headers = Record::<BufferedBody>::new();
+        let record = headers.add_body(
+            "HTTP/1.1 200 OK\r\nContent-Type: unknown/unknown\r\n\r\n<!DOCTYPE html><title>",
+        );
+
+        let response_head = HttpResponseHead::parse(record.body()).unwrap();
+        let content_type = RecordContentType::new(&record, &response_head).unwrap();
+
+        assert_eq!(content_type.mime, "text/html");
+        assert_eq!(
+            content_type.declared_mime.as_deref(),
+            Some("unknown/unknown")
+        );
+        assert_eq!(content_type.sniffed_mime.as_deref(), Some("text/html"));
+    }
+
+    #[test]
+    fn sniffed_content_type_of_encoded_payload() {
+        let mut encoder = libflate::gzip::Encoder::new(Vec::new()).unwrap();
+        std::io::Write::write_all(&mut encoder, b"<!DOCTYPE html><title>Example</title>").unwrap();
+        let mut body = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\n\r\n".to_vec();
+        body.extend(encoder.finish().into_result().unwrap());
+        let headers = Record::<BufferedBody>::new();
+        let record = headers.add_body(body);
+
+        let response_head = HttpResponseHead::parse(record.body()).unwrap();
+        let content_type = RecordContentType::new(&record, &response_head).unwrap();
+
+        assert_eq!(content_type.mime, "text/html");
+        assert_eq!(content_type.declared_mime, None);
+    }
 }
@@ -25,6 +25,11 @@ impl RecordDigest {
         }
     }
 }
+impl From<String> for RecordDigest {
+    fn from(value: String) -> Self {
+        return Self(value);
+    }
+}
 impl fmt::Display for RecordDigest {
     fn fmt(&self, message: &mut fmt::Formatter) -> fmt::Result {
         return write!(message, "{}", self.0);
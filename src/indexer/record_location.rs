@@ -0,0 +1,460 @@
+use libflate::gzip;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read};
+use warc::{BufferedBody, Record, WarcReader};
+
+const MB: usize = 1_048_576;
+
+/// The start of every gzip member using deflate, which is
+/// every gzip member in practice.
+const GZIP_MEMBER_START: &[u8] = &[0x1f, 0x8b, 0x08];
+
+/// The start of every WARC record, after the end of the line before it.
+const WARC_RECORD_START: &[u8] = b"\nWARC/1.";
+
+/// Where a WARC record sits in the file on disk.
+///
+/// For a gzipped WARC file this is the position of the gzip _member_
+/// holding the record, as this is what replay tools need to seek to and
+/// decompress. For an uncompressed WARC file it is the position of the
+/// record itself, including the two blank lines after the record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecordLocation {
+    /// The byte offset where the record starts
+    pub offset: u64,
+    /// The length in bytes of the record on disk
+    pub length: u64,
+}
+
+/// A record read from a WARC file, along with its location.
+///
+/// The location is `None` if the record cannot be found on its own, which
+/// happens when several records are compressed together into a single gzip member.
+pub type LocatedRecord = (
+    Result<Record<BufferedBody>, warc::Error>,
+    Option<RecordLocation>,
+);
+
+/// Wraps a reader and keeps a count of the bytes consumed from it.
+///
+/// While resyncing after a damaged record, the bytes consumed can also
+/// be recorded, and put back to be read again once the start of the
+/// next record has been found among them.
+struct CountingReader<Reader> {
+    reader: Reader,
+    bytes_read: u64,
+    unread_bytes: VecDeque<u8>,
+    recorded_bytes: Option<Vec<u8>>,
+}
+impl<Reader: BufRead> CountingReader<Reader> {
+    const fn new(reader: Reader) -> Self {
+        return Self {
+            reader,
+            bytes_read: 0,
+            unread_bytes: VecDeque::new(),
+            recorded_bytes: None,
+        };
+    }
+    /// Start keeping a copy of every byte consumed.
+    fn start_recording(&mut self) {
+        self.recorded_bytes = Some(Vec::new());
+    }
+    /// Stop recording, and return the bytes consumed since recording started.
+    fn stop_recording(&mut self) -> Vec<u8> {
+        return self.recorded_bytes.take().unwrap_or_default();
+    }
+    /// Put bytes back in front of the reader, so they are read again.
+    fn unread(&mut self, bytes: &[u8]) {
+        for byte in bytes.iter().rev() {
+            self.unread_bytes.push_front(*byte);
+        }
+        self.bytes_read -= bytes.len() as u64;
+    }
+    /// # Resync
+    ///
+    /// Moves the reader on to the start of the next record after a damaged
+    /// one, and returns the offset it starts at. The bytes consumed while
+    /// reading the damaged record, which started at `offset`, are searched
+    /// first, as the next record may already have been read into them.
+    ///
+    /// The record start is found by looking for `marker`, the first `skip`
+    /// bytes of which come before the record. Returns the end of the file
+    /// if there are no more records.
+    fn resync(
+        &mut self,
+        offset: u64,
+        consumed_bytes: &[u8],
+        marker: &[u8],
+        skip: usize,
+    ) -> io::Result<u64> {
+        // start searching one byte in, so the reader always moves on
+        if let Some(position) = consumed_bytes.get(1..).and_then(|bytes| {
+            return bytes
+                .windows(marker.len())
+                .position(|window| return window == marker);
+        }) {
+            let record_start = position + 1 + skip;
+            self.unread(&consumed_bytes[record_start..]);
+            return Ok(offset + record_start as u64);
+        }
+
+        // otherwise keep reading until the marker turns up,
+        // including the end of the bytes already consumed
+        let mut window: VecDeque<u8> = consumed_bytes
+            .iter()
+            .skip(consumed_bytes.len().saturating_sub(marker.len() - 1))
+            .copied()
+            .collect();
+        let mut byte = [0_u8; 1];
+        loop {
+            if self.read(&mut byte)? == 0 {
+                return Ok(self.bytes_read);
+            }
+            window.push_back(byte[0]);
+            if window.len() > marker.len() {
+                window.pop_front();
+            }
+            if window.iter().eq(marker.iter())
+                && self.bytes_read - (marker.len() - skip) as u64 > offset
+            {
+                let (_, record_bytes) = window.make_contiguous().split_at(skip);
+                let record_bytes = record_bytes.to_vec();
+                self.unread(&record_bytes);
+                return Ok(self.bytes_read);
+            }
+        }
+    }
+    /// # Skip damaged member
+    ///
+    /// Returns the location of a damaged gzip member which started at
+    /// `offset`. If `resync` is set, the reader is first moved on to the
+    /// start of the next gzip member, otherwise the member ends wherever
+    /// reading it stopped.
+    fn skip_damaged_member(&mut self, offset: u64, resync: bool) -> io::Result<RecordLocation> {
+        let consumed_bytes = self.stop_recording();
+        if resync {
+            let member_start = self.resync(offset, &consumed_bytes, GZIP_MEMBER_START, 0)?;
+            return Ok(RecordLocation {
+                offset,
+                length: member_start - offset,
+            });
+        }
+        return Ok(RecordLocation {
+            offset,
+            length: self.bytes_read - offset,
+        });
+    }
+}
+impl<Reader: BufRead> Read for CountingReader<Reader> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = if self.unread_bytes.is_empty() {
+            self.reader.read(buffer)?
+        } else {
+            self.unread_bytes.read(buffer)?
+        };
+        if let Some(recorded_bytes) = &mut self.recorded_bytes {
+            recorded_bytes.extend_from_slice(&buffer[..bytes_read]);
+        }
+        self.bytes_read += bytes_read as u64;
+        return Ok(bytes_read);
+    }
+}
+impl<Reader: BufRead> BufRead for CountingReader<Reader> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.unread_bytes.is_empty() {
+            return self.reader.fill_buf();
+        } else {
+            return Ok(self.unread_bytes.as_slices().0);
+        }
+    }
+    fn consume(&mut self, amount: usize) {
+        if let Some(recorded_bytes) = &mut self.recorded_bytes {
+            if self.unread_bytes.is_empty() {
+                if let Ok(buffer) = self.reader.fill_buf() {
+                    recorded_bytes.extend_from_slice(&buffer[..amount]);
+                }
+            } else {
+                recorded_bytes.extend(self.unread_bytes.iter().take(amount));
+            }
+        }
+        self.bytes_read += amount as u64;
+        if self.unread_bytes.is_empty() {
+            return self.reader.consume(amount);
+        } else {
+            return self.unread_bytes.consume(amount);
+        }
+    }
+}
+
+/// # Read uncompressed records
+///
+/// Iterates over the records in an uncompressed WARC file, counting
+/// the bytes consumed by the WARC reader to locate each record.
+///
+/// If `resync` is set, a record which cannot be read is skipped over to
+/// the next line starting `WARC/1.`, and the error is returned with the
+/// location of the damaged bytes. Otherwise reading carries on from
+/// wherever the damaged record ended.
+pub fn uncompressed_records<Reader: Read>(
+    warc_reader: Reader,
+    resync: bool,
+) -> impl Iterator<Item = LocatedRecord> {
+    let mut reader = CountingReader::new(BufReader::with_capacity(MB, warc_reader));
+    return std::iter::from_fn(move || {
+        let offset = reader.bytes_read;
+        if resync {
+            reader.start_recording();
+        }
+        let record = WarcReader::new(&mut reader).iter_records().next()?;
+        let consumed_bytes = reader.stop_recording();
+        match record {
+            Err(warc_error) if resync => {
+                let location = match reader.resync(offset, &consumed_bytes, WARC_RECORD_START, 1) {
+                    Ok(record_start) => RecordLocation {
+                        offset,
+                        length: record_start - offset,
+                    },
+                    Err(io_error) => return Some((Err(warc::Error::ReadData(io_error)), None)),
+                };
+                return Some((Err(warc_error), Some(location)));
+            }
+            record => {
+                let length = reader.bytes_read - offset;
+                return Some((record, Some(RecordLocation { offset, length })));
+            }
+        }
+    });
+}
+
+/// A gzip member part way through being read, one record at a time.
+struct GzipMember<Reader: BufRead> {
+    records: BufReader<gzip::Decoder<CountingReader<Reader>>>,
+    offset: u64,
+    records_read: usize,
+    /// The first record is held back until the end of the member is
+    /// found, so that its length is known
+    first_record: Option<Record<BufferedBody>>,
+}
+impl<Reader: BufRead> GzipMember<Reader> {
+    /// # Finish member
+    ///
+    /// Reads to the end of the member, and returns the reader, the
+    /// location of the member, and the first record, along with the
+    /// result of decompressing the rest of the member.
+    fn finish(
+        mut self,
+    ) -> (
+        CountingReader<Reader>,
+        RecordLocation,
+        Option<Record<BufferedBody>>,
+        io::Result<u64>,
+    ) {
+        let drained = io::copy(&mut self.records, &mut io::sink());
+        let reader = self.records.into_inner().into_inner();
+        let location = RecordLocation {
+            offset: self.offset,
+            length: reader.bytes_read - self.offset,
+        };
+        return (reader, location, self.first_record, drained);
+    }
+}
+
+/// # Read gzipped records
+///
+/// Iterates over the records in a gzipped WARC file, decompressing
+/// one gzip member at a time and counting the compressed bytes
+/// consumed to find where each member starts and ends.
+///
+/// Records are parsed straight from the decompressed stream, so a
+/// gzip member is never held in memory. Normally there is exactly one
+/// record in each member, which is returned once the end of the member
+/// is found. Any further records share the member, so they cannot be
+/// located, and are returned as they are read, before the first one.
+///
+/// If `resync` is set, a gzip member which cannot be decompressed is
+/// skipped over to the start of the next gzip member, and the error is
+/// returned with the location of the damaged bytes.
+pub fn gzipped_records<Reader: Read>(
+    warc_reader: Reader,
+    resync: bool,
+) -> impl Iterator<Item = LocatedRecord> {
+    let mut reader = Some(CountingReader::new(BufReader::with_capacity(
+        MB,
+        warc_reader,
+    )));
+    let mut member: Option<GzipMember<_>> = None;
+    let mut damaged_records: VecDeque<LocatedRecord> = VecDeque::new();
+    return std::iter::from_fn(move || {
+        loop {
+            if let Some(located_record) = damaged_records.pop_front() {
+                return Some(located_record);
+            }
+
+            if let Some(current_member) = &mut member {
+                let record = WarcReader::new(&mut current_member.records)
+                    .iter_records()
+                    .next();
+                current_member.records_read += 1;
+                match record {
+                    Some(Ok(record)) if current_member.records_read == 1 => {
+                        current_member.first_record = Some(record);
+                    }
+                    Some(Ok(record)) => return Some((Ok(record), None)),
+                    Some(Err(warc_error)) => {
+                        let (mut finished_reader, location, first_record, drained) =
+                            member.take()?.finish();
+                        // only resync if the member could not be decompressed
+                        let skipped = finished_reader
+                            .skip_damaged_member(location.offset, resync && drained.is_err());
+                        reader = Some(finished_reader);
+                        let location = match skipped {
+                            Ok(location) => location,
+                            Err(resync_error) => {
+                                return Some((Err(warc::Error::ReadData(resync_error)), None));
+                            }
+                        };
+                        // the member is damaged, so any record read
+                        // from it before the error cannot be located
+                        if let Some(first_record) = first_record {
+                            damaged_records.push_back((Ok(first_record), None));
+                        }
+                        damaged_records.push_back((Err(warc_error), Some(location)));
+                    }
+                    None => {
+                        let (mut finished_reader, location, first_record, drained) =
+                            member.take()?.finish();
+                        finished_reader.stop_recording();
+                        reader = Some(finished_reader);
+                        if let Err(io_error) = drained {
+                            return Some((Err(warc::Error::ReadData(io_error)), Some(location)));
+                        }
+                        if let Some(first_record) = first_record {
+                            return Some((Ok(first_record), Some(location)));
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let mut next_reader = reader.take()?;
+            // check for the end of the file before
+            // trying to read the next gzip member
+            match next_reader.fill_buf() {
+                Ok([]) => return None,
+                Ok(_) => {}
+                Err(io_error) => {
+                    reader = Some(next_reader);
+                    return Some((Err(warc::Error::ReadData(io_error)), None));
+                }
+            }
+
+            let offset = next_reader.bytes_read;
+            // the decoder takes the reader, and drops it if the gzip header
+            // is damaged, so read the header once to check it, then put
+            // it back before handing the reader over
+            next_reader.start_recording();
+            if let Err(header_error) = gzip::Decoder::new(&mut next_reader) {
+                let location = match next_reader.skip_damaged_member(offset, resync) {
+                    Ok(location) => location,
+                    Err(resync_error) => {
+                        return Some((Err(warc::Error::ReadData(resync_error)), None));
+                    }
+                };
+                reader = Some(next_reader);
+                return Some((Err(warc::Error::ReadData(header_error)), Some(location)));
+            }
+            let header_bytes = next_reader.stop_recording();
+            next_reader.unread(&header_bytes);
+            if resync {
+                next_reader.start_recording();
+            }
+            match gzip::Decoder::new(next_reader) {
+                Ok(decoder) => {
+                    member = Some(GzipMember {
+                        records: BufReader::new(decoder),
+                        offset,
+                        records_read: 0,
+                        first_record: None,
+                    });
+                }
+                // the reader is gone, so there is nothing more to read
+                Err(io_error) => return Some((Err(warc::Error::ReadData(io_error)), None)),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn gzip_member_locations() {
+        let locations: Vec<Option<RecordLocation>> =
+            gzipped_records(std::fs::File::open("tests/example.warc.gz").unwrap(), false)
+                .map(|(_, location)| return location)
+                .collect();
+
+        assert_eq!(
+            locations[1],
+            Some(RecordLocation {
+                offset: 278,
+                length: 1288
+            })
+        );
+    }
+
+    #[test]
+    fn resync_after_damaged_member() {
+        let mut warc_bytes = std::fs::read("tests/example.warc.gz").unwrap();
+        // corrupt the deflate stream of the second member
+        warc_bytes[300..320].fill(0xff);
+        let records: Vec<LocatedRecord> = gzipped_records(warc_bytes.as_slice(), true).collect();
+
+        assert!(records[1].0.is_err());
+        assert_eq!(
+            records[1].1,
+            Some(RecordLocation {
+                offset: 278,
+                length: 1288
+            })
+        );
+        assert_eq!(records.len(), 6);
+        assert!(records[2..].iter().all(|(record, _)| return record.is_ok()));
+    }
+
+    #[test]
+    fn records_sharing_a_gzip_member() {
+        let mut warc_content = Vec::new();
+        gzip::MultiDecoder::new(std::fs::File::open("tests/example.warc.gz").unwrap())
+            .unwrap()
+            .read_to_end(&mut warc_content)
+            .unwrap();
+        // compress the whole file into a single gzip member
+        let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+        io::copy(&mut warc_content.as_slice(), &mut encoder).unwrap();
+        let warc_bytes = encoder.finish().into_result().unwrap();
+        let records: Vec<LocatedRecord> = gzipped_records(warc_bytes.as_slice(), false).collect();
+
+        assert_eq!(records.len(), 6);
+        assert!(records.iter().all(|(record, _)| return record.is_ok()));
+        // the first record in the member comes last, once its length is known
+        assert!(
+            records[..5]
+                .iter()
+                .all(|(_, location)| return location.is_none())
+        );
+        assert_eq!(
+            records[5].1,
+            Some(RecordLocation {
+                offset: 0,
+                length: warc_bytes.len() as u64
+            })
+        );
+        assert_eq!(
+            records[5].0.as_ref().unwrap().warc_type(),
+            &warc::RecordType::WarcInfo
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use crate::indexer::{CDXJIndexRecord, HttpRequestHead, indexing_errors::IndexingError};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde_json::Value;
+use std::collections::HashMap;
+use url::form_urlencoded;
+use warc::{BufferedBody, Record, RecordType, WarcHeader};
+
+/// The method and body of an HTTP request, from a WARC `request` record.
+///
+/// Replay tools such as pywb and ReplayWeb.page tell apart captures of
+/// `POST` and `PUT` requests to the same url by adding the method and
+/// request body to the query string before making the searchable url,
+/// for example `__wb_method=post&q=example`. The body is converted in
+/// the same way as [warcio.js](https://github.com/webrecorder/warcio.js):
+///
+/// * form data is added as it is
+/// * json is flattened into a parameter for each value
+/// * anything else is base64 encoded into a `__wb_post_data` parameter
+pub struct RecordRequest {
+    /// The HTTP request method, such as `POST`
+    pub method: String,
+    /// The query parameters made from the method and request body
+    pub request_body: String,
+}
+
+impl RecordRequest {
+    /// # Parse record request
+    ///
+    /// Parses the [HTTP request](HttpRequestHead) in the record body, and
+    /// converts the method and body into query parameters. A chunked
+    /// body has its chunks joined first.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RecordRequestError` if the HTTP request cannot be
+    /// parsed, this either wraps `httparse::Error`, or explains that
+    /// the request has no method.
+    pub fn new(record: &Record<BufferedBody>) -> Result<Self, IndexingError> {
+        let request_head = match HttpRequestHead::parse(record.body()) {
+            Ok(request_head) => request_head,
+            Err(http_parsing_error) => {
+                return Err(IndexingError::RecordRequestError(
+                    http_parsing_error.to_string(),
+                ));
+            }
+        };
+        let Some(method) = &request_head.method else {
+            return Err(IndexingError::RecordRequestError(
+                "request method is empty".to_owned(),
+            ));
+        };
+        let content_type = request_head
+            .header_values("content-type")
+            .next()
+            .map(|content_type| return String::from_utf8_lossy(content_type).into_owned());
+
+        return Ok(Self {
+            method: method.to_uppercase(),
+            request_body: request_query(
+                method,
+                content_type.as_deref(),
+                &request_head.body(record.body()),
+            ),
+        });
+    }
+    /// Returns `true` if the request needs its own searchable url,
+    /// which is any method other than `GET` or `HEAD`.
+    #[must_use]
+    pub fn is_indexed(&self) -> bool {
+        return !["GET", "HEAD"].contains(&self.method.as_str());
+    }
+}
+
+/// Convert the method and body of a request into query parameters.
+fn request_query(method: &str, content_type: Option<&str>, body: &[u8]) -> String {
+    let mut query = form_urlencoded::Serializer::new(String::new());
+    query.append_pair("__wb_method", &method.to_lowercase());
+    if body.is_empty() {
+        return query.finish();
+    }
+
+    // ignore any parameters, such as the charset
+    let mime = content_type
+        .and_then(|content_type| return content_type.split(';').next())
+        .map(|mime| return mime.trim().to_lowercase());
+    let body_query = match mime.as_deref() {
+        Some("application/x-www-form-urlencoded") => {
+            Some(String::from_utf8_lossy(body).trim().to_owned())
+        }
+        Some("application/json" | "text/plain") => serde_json::from_slice::<Value>(body)
+            .ok()
+            .map(|json| return json_query(&json)),
+        _ => None,
+    };
+    match body_query {
+        Some(body_query) if body_query.is_empty() => return query.finish(),
+        Some(body_query) => return format!("{}&{body_query}", query.finish()),
+        None => {
+            query.append_pair("__wb_post_data", &BASE64.encode(body));
+            return query.finish();
+        }
+    }
+}
+
+/// Flatten json into a query parameter for each value, named after the
+/// key it belongs to, in the order they appear in the json. Repeated
+/// keys are numbered, so the second `id` becomes `id.2_`, and empty
+/// values are left out.
+fn json_query(json: &Value) -> String {
+    fn add_value(
+        query: &mut form_urlencoded::Serializer<'_, String>,
+        key_counts: &mut HashMap<String, usize>,
+        key: &str,
+        value: &Value,
+    ) {
+        let query_value = match value {
+            Value::Object(object) => {
+                for (object_key, object_value) in object {
+                    add_value(query, key_counts, object_key, object_value);
+                }
+                return;
+            }
+            Value::Array(array) => {
+                for array_value in array {
+                    add_value(query, key_counts, key, array_value);
+                }
+                return;
+            }
+            Value::Null => return,
+            Value::String(string) => string.clone(),
+            Value::Bool(boolean) => boolean.to_string(),
+            Value::Number(number) => number.to_string(),
+        };
+        if query_value.is_empty() {
+            return;
+        }
+        let key_count = key_counts.entry(key.to_owned()).or_insert(0);
+        *key_count += 1;
+        if *key_count == 1 {
+            query.append_pair(key, &query_value);
+        } else {
+            query.append_pair(&format!("{key}.{key_count}_"), &query_value);
+        }
+    }
+
+    let mut query = form_urlencoded::Serializer::new(String::new());
+    add_value(&mut query, &mut HashMap::new(), "", json);
+    return query.finish();
+}
+
+/// Where a line is in the index, by its WARC file name and offset,
+/// which stay the same as the index is sorted and merged.
+type LinePosition = (String, u64);
+
+/// Matches up requests with the responses they were made for.
+///
+/// Either record can point at the other with `WARC-Concurrent-To`, and
+/// the request can come before or after the response, or even be in a
+/// different WARC file, so the lines for both are collected while
+/// indexing and paired up once every WARC file has been read.
+#[derive(Default)]
+pub struct RequestPairs {
+    /// Request lines, by record id
+    requests: HashMap<String, LinePosition>,
+    /// Request lines, by the record they are concurrent to
+    concurrent_requests: HashMap<String, LinePosition>,
+    /// The record id, concurrent record and line of each response
+    responses: Vec<(String, Option<String>, LinePosition)>,
+}
+impl RequestPairs {
+    /// Add a record which has been indexed as this line.
+    pub fn add(&mut self, record: &Record<BufferedBody>, cdxj_record: &CDXJIndexRecord) {
+        let line_position = (cdxj_record.filename.to_string(), cdxj_record.offset);
+        let concurrent_to = record
+            .header(WarcHeader::ConcurrentTo)
+            .map(|record_id| return record_id.into_owned());
+        match record.warc_type() {
+            RecordType::Request => {
+                if let Some(concurrent_to) = concurrent_to {
+                    self.concurrent_requests
+                        .insert(concurrent_to, line_position.clone());
+                }
+                self.requests
+                    .insert(record.warc_id().to_owned(), line_position);
+            }
+            RecordType::Response | RecordType::Revisit => {
+                self.responses
+                    .push((record.warc_id().to_owned(), concurrent_to, line_position));
+            }
+            _ => {}
+        }
+    }
+    /// Give each response with a request the same method, request
+    /// body and searchable url as the request line. The index needs
+    /// sorting again afterwards, as the searchable urls change.
+    pub fn pair(self, cdxj_index: &mut [CDXJIndexRecord]) {
+        let line_numbers: HashMap<LinePosition, usize> = cdxj_index
+            .iter()
+            .enumerate()
+            .map(|(line_number, cdxj_record)| {
+                return (
+                    (cdxj_record.filename.to_string(), cdxj_record.offset),
+                    line_number,
+                );
+            })
+            .collect();
+        for (record_id, concurrent_to, response_position) in self.responses {
+            let Some(request_position) = self.concurrent_requests.get(&record_id).or_else(|| {
+                return concurrent_to
+                    .as_ref()
+                    .and_then(|concurrent_to| return self.requests.get(concurrent_to));
+            }) else {
+                continue;
+            };
+            let (Some(&request_line_number), Some(&response_line_number)) = (
+                line_numbers.get(request_position),
+                line_numbers.get(&response_position),
+            ) else {
+                continue;
+            };
+            let request_line = &cdxj_index[request_line_number];
+            let (Some(method), Some(request_body)) = (
+                request_line.method.clone(),
+                request_line.request_body.clone(),
+            ) else {
+                continue;
+            };
+            let response_line = &mut cdxj_index[response_line_number];
+            // if the response url is different and cannot take the
+            // request body, it keeps its plain searchable url
+            if let Ok(searchable_url) = response_line
+                .url
+                .as_searchable_string_with_request(&request_body)
+            {
+                response_line.searchable_url = searchable_url;
+                response_line.method = Some(method);
+                response_line.request_body = Some(request_body);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn json_post_request() {
+        let mut headers = Record::<BufferedBody>::new();
+        headers.set_warc_type(RecordType::Request);
+        let record = headers.add_body(
+            "POST /search HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/json; charset=utf-8\r\n\r\n{\"query\":\"web archive\",\"tags\":[\"a\",\"b\"],\"page\":2}",
+        );
+
+        let request = RecordRequest::new(&record).unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(
+            request.request_body,
+            "__wb_method=post&query=web+archive&tags=a&tags.2_=b&page=2"
+        );
+    }
+
+    #[test]
+    fn binary_post_request() {
+        let mut headers = Record::<BufferedBody>::new();
+        headers.set_warc_type(RecordType::Request);
+        let record = headers.add_body(
+            "PUT /upload HTTP/1.1\r\nContent-Type: application/octet-stream\r\n\r\nhello",
+        );
+
+        let request = RecordRequest::new(&record).unwrap();
+
+        assert_eq!(
+            request.request_body,
+            "__wb_method=put&__wb_post_data=aGVsbG8%3D"
+        );
+    }
+
+    #[test]
+    fn chunked_post_request_with_many_headers() {
+        let header_lines: String = (0..100)
+            .map(|header_number| return format!("X-Header-{header_number}: {header_number}\r\n"))
+            .collect();
+        let mut headers = Record::<BufferedBody>::new();
+        headers.set_warc_type(RecordType::Request);
+        let record = headers.add_body(format!(
+            "POST /search HTTP/1.1\r\n{header_lines}Content-Type: application/x-www-form-urlencoded\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nq=web\r\n8\r\n+archive\r\n0\r\n\r\n"
+        ));
+
+        let request = RecordRequest::new(&record).unwrap();
+
+        assert_eq!(request.request_body, "__wb_method=post&q=web+archive");
+    }
+}
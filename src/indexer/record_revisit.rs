@@ -0,0 +1,194 @@
+use crate::indexer::{
+    CDXJIndexRecord, HttpResponseHead, RecordContentType, RecordStatus, RecordTimestamp, RecordUrl,
+    WarcFilename, indexing_errors::IndexingError,
+};
+use chrono::DateTime;
+use std::{collections::HashMap, str::FromStr as _};
+use warc::{BufferedBody, Record, WarcHeader};
+
+/// Where to find the original capture of a `revisit` record.
+///
+/// A revisit record stands in for a capture whose payload was the same as
+/// an earlier one, so replay tools need to find the original to get the
+/// payload. The url and date of the original are taken from the WARC
+/// headers if they are present, and the original can be
+/// [resolved](crate::indexer::CDXJIndex::resolve_revisits) to a
+/// location if it is in the same index.
+pub struct RecordRevisit {
+    /// Why the payload was not archived again, from `WARC-Profile`
+    pub profile: Option<String>,
+    /// The url of the original capture, from `WARC-Refers-To-Target-URI`
+    pub refers_to_url: Option<RecordUrl>,
+    /// When the original capture was made, from `WARC-Refers-To-Date`
+    pub refers_to_date: Option<RecordTimestamp>,
+    /// The media type of the original payload, from the
+    /// HTTP headers of the revisit or from the original
+    pub original_mime: Option<RecordContentType>,
+    /// The location of the original capture, once resolved
+    pub original: Option<RevisitOriginal>,
+    /// Errors from reading `WARC-Refers-To-Target-URI` or
+    /// `WARC-Refers-To-Date` headers which were present but malformed,
+    /// and have been left out
+    pub header_errors: Vec<IndexingError>,
+}
+
+/// The location of the original capture of a revisit record.
+pub struct RevisitOriginal {
+    /// The WARC file where the original record is located
+    pub filename: WarcFilename,
+    /// The byte offset for the original record
+    pub offset: u64,
+    /// The length in bytes of the original record
+    pub length: u64,
+}
+
+impl RecordRevisit {
+    /// # Read revisit headers
+    ///
+    /// Reads the `WARC-Profile`, `WARC-Refers-To-Target-URI` and
+    /// `WARC-Refers-To-Date` headers, along with the content type
+    /// from the HTTP headers, if they are stored in the revisit record.
+    ///
+    /// The url and date of the original capture are only hints for
+    /// finding it, so if either cannot be parsed it is treated as
+    /// missing, and the error is kept in `header_errors`.
+    #[must_use]
+    pub fn new(record: &Record<BufferedBody>, response_head: Option<&HttpResponseHead>) -> Self {
+        let mut header_errors = Vec::new();
+        let refers_to_url =
+            match record.header(WarcHeader::Unknown("warc-refers-to-target-uri".to_owned())) {
+                Some(refers_to_url) => match RecordUrl::from_str(&refers_to_url) {
+                    Ok(parsed_url) => Some(parsed_url),
+                    Err(parsing_error) => {
+                        header_errors.push(parsing_error);
+                        None
+                    }
+                },
+                None => None,
+            };
+        let refers_to_date =
+            match record.header(WarcHeader::Unknown("warc-refers-to-date".to_owned())) {
+                Some(refers_to_date) => match DateTime::parse_from_rfc3339(&refers_to_date) {
+                    Ok(parsed_datetime) => Some(RecordTimestamp::from(parsed_datetime)),
+                    Err(parsing_error) => {
+                        header_errors.push(IndexingError::RecordTimestampError(parsing_error));
+                        None
+                    }
+                },
+                None => None,
+            };
+        return Self {
+            profile: record
+                .header(WarcHeader::Profile)
+                .map(|profile| return profile.into_owned()),
+            refers_to_url,
+            refers_to_date,
+            original_mime: response_head.and_then(|response_head| {
+                return RecordContentType::from_http_headers(response_head).ok();
+            }),
+            original: None,
+            header_errors,
+        };
+    }
+}
+
+/// # Resolve revisits
+///
+/// Finds the original capture of each revisit record among the other
+/// records, and fills in its location, along with the status and media
+/// type if the revisit does not have them.
+///
+/// If the revisit has a `WARC-Refers-To-Date`, the original is the
+/// record with that url and timestamp. Otherwise it is the latest record
+/// with the same url and digest, captured no later than the revisit.
+/// Revisits which are already resolved are left as they are.
+pub fn resolve_revisits(cdxj_records: &mut [CDXJIndexRecord]) {
+    // only records which are not revisits can be originals
+    let mut originals_by_date: HashMap<(String, String), usize> = HashMap::new();
+    let mut originals_by_digest: HashMap<String, Vec<usize>> = HashMap::new();
+    for (position, cdxj_record) in cdxj_records.iter().enumerate() {
+        if cdxj_record.revisit.is_some() || cdxj_record.method.is_some() {
+            continue;
+        }
+        originals_by_date
+            .entry((
+                cdxj_record.url.to_string(),
+                cdxj_record.timestamp.to_string(),
+            ))
+            .or_insert(position);
+        if let Some(digest) = &cdxj_record.digest {
+            originals_by_digest
+                .entry(digest.to_string())
+                .or_default()
+                .push(position);
+        }
+    }
+
+    for position in 0..cdxj_records.len() {
+        let revisit_record = &cdxj_records[position];
+        let Some(revisit) = &revisit_record.revisit else {
+            continue;
+        };
+        if revisit.original.is_some() {
+            continue;
+        }
+        let original_url = revisit
+            .refers_to_url
+            .as_ref()
+            .unwrap_or(&revisit_record.url)
+            .to_string();
+        let original_position = match &revisit.refers_to_date {
+            Some(refers_to_date) => originals_by_date
+                .get(&(original_url, refers_to_date.to_string()))
+                .copied(),
+            None => revisit_record
+                .digest
+                .as_ref()
+                .and_then(|digest| return originals_by_digest.get(&digest.to_string()))
+                .and_then(|positions| {
+                    return positions
+                        .iter()
+                        .copied()
+                        .filter(|candidate| {
+                            let candidate = &cdxj_records[*candidate];
+                            return candidate.url.to_string() == original_url
+                                && candidate.timestamp <= revisit_record.timestamp;
+                        })
+                        .max_by(|first_candidate, second_candidate| {
+                            return cdxj_records[*first_candidate]
+                                .timestamp
+                                .cmp(&cdxj_records[*second_candidate].timestamp);
+                        });
+                }),
+        };
+        let Some(original_position) = original_position else {
+            continue;
+        };
+
+        let original_record = &cdxj_records[original_position];
+        let original = RevisitOriginal {
+            filename: WarcFilename::from(original_record.filename.to_string()),
+            offset: original_record.offset,
+            length: original_record.length,
+        };
+        let original_status = original_record
+            .status
+            .as_ref()
+            .map(|status| return RecordStatus(status.0));
+        let original_mime = original_record
+            .mime
+            .as_ref()
+            .map(|mime| return RecordContentType::from(mime.to_string()));
+
+        let revisit_record = &mut cdxj_records[position];
+        if revisit_record.status.is_none() {
+            revisit_record.status = original_status;
+        }
+        if let Some(revisit) = &mut revisit_record.revisit {
+            revisit.original = Some(original);
+            if revisit.original_mime.is_none() {
+                revisit.original_mime = original_mime;
+            }
+        }
+    }
+}
@@ -1,6 +1,5 @@
-use crate::indexer::indexing_errors::IndexingError;
+use crate::indexer::{HttpResponseHead, indexing_errors::IndexingError};
 use std::fmt;
-use warc::{BufferedBody, Record};
 
 #[derive(PartialEq, Eq)]
 pub struct RecordStatus(pub u16);
@@ -8,33 +7,22 @@ pub struct RecordStatus(pub u16);
 impl RecordStatus {
     /// # Parse record status
     ///
-    /// Parse the record body with httparse and get the status code
-    /// from the response.
+    /// Get the status code from the [HTTP response](HttpResponseHead)
+    /// in the record body.
     ///
     /// # Errors
     ///
-    /// Returns a `RecordStatusError`, which can contain either
-    /// a _parsing_ error from httparse, or an error arising
-    /// from an empty response code.
-    pub fn new(record: &Record<BufferedBody>) -> Result<Self, IndexingError> {
-        let mut headers = [httparse::EMPTY_HEADER; 64];
-        let mut response = httparse::Response::new(&mut headers);
-
-        match response.parse(record.body()) {
-            Ok(_) => match response.code {
-                Some(response_code) => return Ok(Self(response_code)),
-                None => {
-                    return Err(IndexingError::RecordStatusError(
-                        "response code is empty".to_owned(),
-                    ));
-                }
-            },
-            Err(http_parsing_error) => {
+    /// Returns a `RecordStatusError` if the status line
+    /// is cut off before the response code.
+    pub fn new(response_head: &HttpResponseHead) -> Result<Self, IndexingError> {
+        match response_head.status {
+            Some(response_code) => return Ok(Self(response_code)),
+            None => {
                 return Err(IndexingError::RecordStatusError(
-                    http_parsing_error.to_string(),
+                    "response code is empty".to_owned(),
                 ));
             }
-        };
+        }
     }
 }
 impl fmt::Display for RecordStatus {
@@ -52,8 +40,8 @@ mod tests {
     fn valid_status() {
         let status = "200";
         let body = format!("HTTP/1.1 {status}\n");
-        let record = Record::<BufferedBody>::new().add_body(body);
-        let generated_status = RecordStatus::new(&record).unwrap().to_string();
+        let response_head = HttpResponseHead::parse(body.as_bytes()).unwrap();
+        let generated_status = RecordStatus::new(&response_head).unwrap().to_string();
 
         assert_eq!(generated_status, status);
     }
@@ -1,10 +1,10 @@
 use crate::indexer::indexing_errors::IndexingError;
-use chrono::DateTime;
+use chrono::{DateTime, NaiveDateTime, SecondsFormat};
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::{fmt, str::FromStr};
 use warc::{BufferedBody, Record, WarcHeader};
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
 pub struct RecordTimestamp(DateTime<chrono::FixedOffset>);
 
 impl RecordTimestamp {
@@ -34,6 +34,45 @@ impl RecordTimestamp {
             }
         }
     }
+    /// # Format timestamp
+    ///
+    /// Returns the timestamp in RFC 3339 format, such
+    /// as `2025-08-06T13:37:28Z` for a UTC timestamp.
+    #[must_use]
+    pub fn to_rfc3339(&self) -> String {
+        return self.0.to_rfc3339_opts(SecondsFormat::Secs, true);
+    }
+    /// # Time between timestamps
+    ///
+    /// Returns the number of seconds between two timestamps,
+    /// whichever comes first.
+    #[must_use]
+    pub const fn seconds_between(&self, other: &Self) -> u64 {
+        return self.0.timestamp().abs_diff(other.0.timestamp());
+    }
+}
+impl From<DateTime<chrono::FixedOffset>> for RecordTimestamp {
+    fn from(value: DateTime<chrono::FixedOffset>) -> Self {
+        return Self(value);
+    }
+}
+/// Parse a 14-digit CDX(J) timestamp such as `20250806133728`,
+/// which is always read as UTC.
+impl FromStr for RecordTimestamp {
+    type Err = IndexingError;
+    fn from_str(timestamp: &str) -> Result<Self, Self::Err> {
+        // chrono accepts single digit fields, so
+        // check the length and characters first
+        if timestamp.len() != 14 || !timestamp.bytes().all(|byte| return byte.is_ascii_digit()) {
+            return Err(IndexingError::IndexParsingError(format!(
+                "expected a 14-digit timestamp, found {timestamp}"
+            )));
+        }
+        match NaiveDateTime::parse_from_str(timestamp, "%Y%m%d%H%M%S") {
+            Ok(parsed_datetime) => return Ok(Self(parsed_datetime.and_utc().fixed_offset())),
+            Err(parsing_error) => return Err(IndexingError::RecordTimestampError(parsing_error)),
+        }
+    }
 }
 impl fmt::Display for RecordTimestamp {
     fn fmt(&self, message: &mut fmt::Formatter) -> fmt::Result {
@@ -56,4 +95,12 @@ mod tests {
 
         assert_eq!(generated_timestamp, "20250806133728");
     }
+
+    #[test]
+    fn timestamp_from_str() {
+        let timestamp = "20250806133728";
+        let parsed_timestamp = RecordTimestamp::from_str(timestamp).unwrap().to_string();
+
+        assert_eq!(parsed_timestamp, timestamp);
+    }
 }
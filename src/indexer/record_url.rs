@@ -1,11 +1,11 @@
 use crate::indexer::indexing_errors::IndexingError;
-use serde::Serialize;
-use std::fmt;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
 use surt_rs::generate_surt;
 use url::Url;
 use warc::{BufferedBody, Record, WarcHeader};
 
-#[derive(Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct RecordUrl(Url);
 
 impl RecordUrl {
@@ -42,6 +42,11 @@ impl RecordUrl {
     /// Returns a `RecordUrlError` as a wrapper for `url::ParseError`
     /// if there is any problem parsing the url.
     pub fn as_searchable_string(&self) -> Result<String, IndexingError> {
+        // urls without a host, such as `urn:pageinfo:https://example.com/`,
+        // cannot be reordered, so these are searched for as they are
+        if self.0.cannot_be_a_base() {
+            return Ok(self.0.as_str().to_owned());
+        }
         match generate_surt(self.0.as_str()) {
             Ok(sorted_url) => return Ok(sorted_url),
             Err(sorting_parse_error) => {
@@ -49,6 +54,42 @@ impl RecordUrl {
             }
         }
     }
+    /// # Compose searchable string with a request body
+    ///
+    /// The same as [`RecordUrl::as_searchable_string`], but with the query
+    /// parameters made from a [request](crate::indexer::RecordRequest)
+    /// added to the end of the query string first.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RecordUrlError` as a wrapper for `url::ParseError`
+    /// if there is any problem parsing the url.
+    pub fn as_searchable_string_with_request(
+        &self,
+        request_body: &str,
+    ) -> Result<String, IndexingError> {
+        let mut request_url = self.0.clone();
+        let query = match request_url.query() {
+            Some(query) if !query.is_empty() => format!("{query}&{request_body}"),
+            _ => request_body.to_owned(),
+        };
+        request_url.set_query(Some(&query));
+        match generate_surt(request_url.as_str()) {
+            Ok(sorted_url) => return Ok(sorted_url),
+            Err(sorting_parse_error) => {
+                return Err(IndexingError::RecordUrlError(sorting_parse_error));
+            }
+        }
+    }
+}
+impl FromStr for RecordUrl {
+    type Err = IndexingError;
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        match Url::parse(url) {
+            Ok(record_url) => return Ok(Self(record_url)),
+            Err(parse_error) => return Err(IndexingError::RecordUrlError(parse_error)),
+        }
+    }
 }
 impl fmt::Display for RecordUrl {
     fn fmt(&self, message: &mut fmt::Formatter) -> fmt::Result {
@@ -94,4 +135,16 @@ mod tests {
 
         assert_eq!(surt_parsed_url, "review,thehtml)/04/ascii-bedroom-archive");
     }
+
+    #[test]
+    fn searchable_urn() {
+        let target_url = "urn:pageinfo:https://example.com/";
+
+        let searchable_url = RecordUrl::from_str(target_url)
+            .unwrap()
+            .as_searchable_string()
+            .unwrap();
+
+        assert_eq!(searchable_url, target_url);
+    }
 }
@@ -3,43 +3,46 @@ use std::fmt;
 use std::path::Path;
 use warc::{BufferedBody, Record, WarcHeader};
 
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
 pub struct WarcFilename(String);
 
 impl WarcFilename {
     /// # Create Warc filename
     ///
-    /// Takes the filename from `WarcHeader::Filename`, and converts it
-    /// to a string. If no filename is found in the record this function
-    /// reads the path to the warc file.
+    /// Takes the filename from the path to the warc file, as this is
+    /// the name the file will have inside the WACZ. The `WarcHeader::Filename`
+    /// field is only used if no filename can be read from the path,
+    /// because it records the name the file was _written_ with, which
+    /// is often not the name it ends up with.
     ///
     /// # Errors
     ///
     /// Returns a `WarcFilenameError` error if the filename cannot be
-    /// inferred from the file path. Normally you should not hit this
-    /// error.
+    /// inferred from either the file path or the record. Normally you
+    /// should not hit this error.
     pub fn new(
         record: &Record<BufferedBody>,
         warc_file_path: &Path,
     ) -> Result<Self, IndexingError> {
-        if let Some(record_filename) = record.header(WarcHeader::Filename) {
-            println!("record filename is {record_filename} from file");
+        if let Some(warc_file_name) = warc_file_path.file_name() {
+            return Ok(Self(warc_file_name.to_string_lossy().to_string()));
+        } else if let Some(record_filename) = record.header(WarcHeader::Filename) {
             return Ok(Self(record_filename.into_owned()));
         } else {
-            // If no filename is found in the record
-            // we get the filename from the file path
-            if let Some(warc_file_path) = warc_file_path.file_name() {
-                return Ok(Self(warc_file_path.to_string_lossy().to_string()));
-            } else {
-                // Hit this error case if the filename
-                // cannot be inferred from the Path
-                return Err(IndexingError::WarcFilenameError(format!(
-                    "Cannot infer filename from {}",
-                    warc_file_path.to_string_lossy()
-                )));
-            }
+            // Hit this error case if the filename
+            // cannot be inferred from the Path
+            return Err(IndexingError::WarcFilenameError(format!(
+                "Cannot infer filename from {}",
+                warc_file_path.to_string_lossy()
+            )));
         }
     }
 }
+impl From<String> for WarcFilename {
+    fn from(value: String) -> Self {
+        return Self(value);
+    }
+}
 impl fmt::Display for WarcFilename {
     fn fmt(&self, message: &mut fmt::Formatter) -> fmt::Result {
         return write!(message, "{}", self.0);
@@ -62,5 +65,16 @@ mod tests {
         assert_eq!(parsed_filename, filename);
     }
 
-    // todo test case, what if the filename and warc are both present and they don't match?
+    #[test]
+    fn mismatched_filename() {
+        let path = Path::new("archive/renamed.warc.gz");
+        let mut headers = Record::<BufferedBody>::new();
+        headers
+            .set_header(WarcHeader::Filename, "original.warc.gz")
+            .unwrap();
+        let record = headers.add_body("");
+        let parsed_filename = WarcFilename::new(&record, path).unwrap().to_string();
+
+        assert_eq!(parsed_filename, "renamed.warc.gz");
+    }
 }
@@ -0,0 +1,110 @@
+use crate::indexer::CDXJIndex;
+use libflate::gzip;
+use sha2::{Digest as _, Sha256};
+use std::fmt::Write as _;
+use std::io::{self, Write as _};
+
+/// The number of CDX(J) lines in each compressed block, this
+/// is the same default as [pywb](https://pywb.readthedocs.io/en/latest/manual/indexing.html#zipnum-sharded-index).
+pub const ZIPNUM_LINES_PER_BLOCK: usize = 3000;
+
+/// The file name of the compressed index inside the WACZ.
+pub const ZIPNUM_INDEX_FILENAME: &str = "index.cdx.gz";
+
+/// A compressed index, also known as a sharded or `ZipNum` index, made up of the gzipped index itself and a
+/// secondary index which records where each block starts.
+///
+/// Large indexes load much faster this way, as replay tools only need
+/// to read the small secondary index and then decompress the one block
+/// which could contain a url.
+pub struct ZipNumIndex {
+    /// The contents of `index.cdx.gz`, a series of gzip
+    /// members each containing a block of CDX(J) lines.
+    pub compressed_index: Vec<u8>,
+    /// The contents of `index.idx`, with one line for each block
+    /// in the compressed index.
+    pub secondary_index: String,
+}
+
+impl CDXJIndex {
+    /// # Compress index
+    ///
+    /// Splits the index into blocks of `lines_per_block` lines, and
+    /// compresses each block into its own gzip member. Each line of the
+    /// secondary index gives the searchable url and timestamp of the first
+    /// record in a block, followed by the offset, length and digest of
+    /// the block in the compressed index:
+    ///
+    /// ```text
+    /// !meta 0 {"format":"cdxj-gzip-1.0","filename":"index.cdx.gz"}
+    /// com,example)/ 20250423121042 {"offset":0,"length":433,"digest":"sha256:…","filename":"index.cdx.gz"}
+    /// ```
+    ///
+    /// The index should be [sorted](CDXJIndex::sort) first, otherwise the
+    /// secondary index cannot be searched.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if anything goes wrong with compressing
+    /// a block.
+    pub fn to_zipnum(&self, lines_per_block: usize) -> io::Result<ZipNumIndex> {
+        let mut compressed_index: Vec<u8> = Vec::new();
+        let mut secondary_index = format!(
+            "!meta 0 {{\"format\":\"cdxj-gzip-1.0\",\"filename\":\"{ZIPNUM_INDEX_FILENAME}\"}}\n"
+        );
+
+        for block in self.0.chunks(lines_per_block.max(1)) {
+            let mut encoder = gzip::Encoder::new(Vec::new())?;
+            for record in block {
+                write!(encoder, "{record}")?;
+            }
+            let compressed_block = encoder.finish().into_result()?;
+
+            let first_record = &block[0];
+            let _ = writeln!(
+                secondary_index,
+                "{} {} {{\"offset\":{},\"length\":{},\"digest\":\"sha256:{:x}\",\"filename\":\"{ZIPNUM_INDEX_FILENAME}\"}}",
+                first_record.searchable_url,
+                first_record.timestamp,
+                compressed_index.len(),
+                compressed_block.len(),
+                Sha256::digest(&compressed_block),
+            );
+            compressed_index.extend(compressed_block);
+        }
+
+        return Ok(ZipNumIndex {
+            compressed_index,
+            secondary_index,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::io::Read as _;
+
+    #[test]
+    fn valid_zipnum_index() {
+        let cdxj_index: CDXJIndex =
+            std::fs::read_to_string("tests/wacz_example/indexes/index.cdxj")
+                .unwrap()
+                .parse()
+                .unwrap();
+        let zipnum_index = cdxj_index.to_zipnum(1).unwrap();
+
+        let secondary_index_lines: Vec<&str> = zipnum_index.secondary_index.lines().collect();
+        assert_eq!(secondary_index_lines.len(), 4);
+        assert!(secondary_index_lines[2].starts_with("com,example)/favicon.ico 20250423121042 "));
+        assert!(secondary_index_lines[3].starts_with("urn:pageinfo:https://example.com/ "));
+
+        let mut decompressed_index = String::new();
+        gzip::MultiDecoder::new(zipnum_index.compressed_index.as_slice())
+            .unwrap()
+            .read_to_string(&mut decompressed_index)
+            .unwrap();
+        assert_eq!(decompressed_index, cdxj_index.to_string());
+    }
+}
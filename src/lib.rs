@@ -11,20 +11,56 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! An existing WACZ file can also be opened and read back into a [WACZ] struct.
+//!
+//! ```
+//! # use std::error::Error;
+//! # use wacksy::WACZ;
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! # let zipped_wacz = WACZ::from_file(std::path::Path::new("tests/example.warc.gz"))?.zip()?;
+//! # std::fs::write("tests/example.wacz", zipped_wacz)?;
+//! let wacz_file_path = std::path::Path::new("tests/example.wacz"); // set path to your ᴡᴀᴄᴢ file
+//! let wacz_object = WACZ::open(wacz_file_path)?; // read the ᴡᴀᴄᴢ back into a ᴡᴀᴄᴢ object
+//! println!("{}", wacz_object.cdxj_index); // print out the ᴄᴅxᴊ index
+//! # Ok(())
+//! # }
+//! ```
 #![doc(
     html_logo_url = "https://www.ox.ac.uk/sites/default/themes/custom/oxweb/images/oxweb-logo.gif",
     html_favicon_url = "https://www.bodleian.ox.ac.uk/sites/default/files/styles/favicon-32x32/public/bodreader/site-favicon/bod-favicon.png"
 )]
 
+pub mod builder;
 pub mod datapackage;
 pub mod indexer;
-use std::{error::Error, fmt, path::Path};
+pub mod reader;
+pub mod replay;
+#[cfg(feature = "signing")]
+pub mod signing;
+pub mod validator;
+pub mod writer;
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt,
+    fs::File,
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+};
 
-use rawzip::{CompressionMethod, ZipArchiveWriter, ZipDataWriter};
+use rawzip::ZipArchive;
 
+#[cfg(feature = "signing")]
+use crate::signing::{SigningError, SigningKey};
 use crate::{
+    builder::{WaczBuilder, ZipCompression},
     datapackage::{DataPackage, DataPackageDigest, DataPackageError},
-    indexer::{CDXJIndex, Index, IndexingError, PageIndex},
+    indexer::{CDXJIndex, CDXJIndexRecord, IndexFormat, IndexingError, PageIndex},
+    reader::{DataPackageFile, ReaderError},
+    replay::{ArchivedResponse, ReplayError},
+    validator::ValidationReport,
+    writer::WriterError,
 };
 
 /// Set the WACZ version of the file being created,
@@ -37,8 +73,21 @@ pub struct WACZ {
     pub datapackage_digest: DataPackageDigest,
     pub cdxj_index: CDXJIndex,
     pub pages_index: PageIndex,
+    /// How each file is compressed when the WACZ is written out
+    pub compression: ZipCompression,
+    /// The `datapackage.json` file as it was read, if the WACZ was opened
+    /// from an archive rather than created
+    datapackage_file: Option<DataPackageFile>,
 }
 impl WACZ {
+    /// # WACZ builder
+    ///
+    /// Starts building a WACZ, so the WARC files, metadata, index format,
+    /// compression and creation time can all be set. See the [builder] module.
+    #[must_use]
+    pub fn builder<'reader>() -> WaczBuilder<'reader> {
+        return WaczBuilder::default();
+    }
     /// # Create WACZ from WARC file
     ///
     /// This is the main function of the library, it takes a path to a WARC file,
@@ -52,114 +101,293 @@ impl WACZ {
     /// datapackage depends on the index being complete, any problem with the
     /// indexer will return early without continuing.
     pub fn from_file(warc_file_path: &Path) -> Result<Self, WaczError> {
-        match Index::index_file(warc_file_path) {
-            Ok(index) => {
-                let datapackage = match DataPackage::new(warc_file_path, &index) {
-                    Ok(datapackage) => datapackage,
-                    Err(datapackage_error) => {
-                        return Err(WaczError::DataPackageError(datapackage_error));
-                    }
-                };
-                let datapackage_digest = match datapackage.digest() {
-                    Ok(digest) => digest,
-                    Err(digest_error) => return Err(WaczError::DataPackageError(digest_error)),
-                };
-
-                return Ok(Self {
-                    datapackage,
-                    datapackage_digest,
-                    cdxj_index: index.cdxj,
-                    pages_index: index.pages,
-                });
+        return Self::from_files(&[warc_file_path.to_path_buf()]);
+    }
+    /// # Create WACZ from multiple WARC files
+    ///
+    /// The same as [`WACZ::from_file`], but takes a list of WARC files, for
+    /// example a crawl split over several rotated files. Each WARC file is
+    /// added to the `archive/` directory under its own name, and the indexes
+    /// of every file are merged into a single CDXJ index and pages list.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WaczError`], see [`WACZ::from_file`]. Two WARC files with
+    /// the same file name will return a [datapackage](DataPackageError) error,
+    /// as they would overwrite each other in the archive.
+    pub fn from_files(warc_file_paths: &[PathBuf]) -> Result<Self, WaczError> {
+        return Self::from_files_with_index_format(warc_file_paths, IndexFormat::default());
+    }
+    /// # Create WACZ with a different index format
+    ///
+    /// The same as [`WACZ::from_files`], but writes the CDX(J) index in the
+    /// given [format](IndexFormat). Use [`IndexFormat::ZipNum`] for archives
+    /// with a lot of records, so they load quickly in replay tools.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WaczError`], see [`WACZ::from_file`].
+    pub fn from_files_with_index_format(
+        warc_file_paths: &[PathBuf],
+        index_format: IndexFormat,
+    ) -> Result<Self, WaczError> {
+        return Self::builder()
+            .warc_files(warc_file_paths)
+            .index_format(index_format)
+            .build();
+    }
+    /// # Open WACZ file
+    ///
+    /// Reads an existing WACZ file from disk and parses the datapackage,
+    /// datapackage digest, CDXJ index and pages list back into a [WACZ] struct.
+    /// The WARC files are left in the archive, and read when they are needed.
+    /// See the [reader] module for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WaczError`] wrapping a [`ReaderError`] if the file cannot
+    /// be opened, is not a valid zip archive, or is missing any of the files
+    /// required by the spec.
+    pub fn open(wacz_file_path: &Path) -> Result<Self, WaczError> {
+        let wacz_file = match File::open(wacz_file_path) {
+            Ok(wacz_file) => wacz_file,
+            Err(io_error) => {
+                return Err(WaczError::ReaderError(ReaderError::FileReadError(io_error)));
+            }
+        };
+        let mut buffer = vec![0_u8; rawzip::RECOMMENDED_BUFFER_SIZE];
+        match ZipArchive::from_file(wacz_file, &mut buffer) {
+            Ok(archive) => match reader::read_archive(archive) {
+                Ok(wacz) => return Ok(wacz),
+                Err(reader_error) => return Err(WaczError::ReaderError(reader_error)),
+            },
+            Err(zip_error) => {
+                return Err(WaczError::ReaderError(ReaderError::ZipError(zip_error)));
+            }
+        }
+    }
+    /// # Read WACZ from a reader
+    ///
+    /// The same as [`WACZ::open`], but takes anything which can be
+    /// read and seeked through, such as a `File` or a `Cursor<Vec<u8>>`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WaczError`] wrapping a [`ReaderError`] if the data is
+    /// not a valid zip archive, or is missing any of the files required
+    /// by the spec.
+    pub fn from_reader<Reader: Read + Seek + Send + 'static>(
+        wacz_reader: Reader,
+    ) -> Result<Self, WaczError> {
+        let mut buffer = vec![0_u8; rawzip::RECOMMENDED_BUFFER_SIZE];
+        match ZipArchive::from_seekable(wacz_reader, &mut buffer) {
+            Ok(archive) => match reader::read_archive(archive) {
+                Ok(wacz) => return Ok(wacz),
+                Err(reader_error) => return Err(WaczError::ReaderError(reader_error)),
+            },
+            Err(zip_error) => {
+                return Err(WaczError::ReaderError(ReaderError::ZipError(zip_error)));
             }
-            Err(indexing_error) => return Err(WaczError::IndexingError(indexing_error)),
+        }
+    }
+    /// # Sign WACZ
+    ///
+    /// Signs the datapackage digest with a local ECDSA P-256 key, so the
+    /// WACZ can be checked with [`WACZ::verify_signature`] once it has
+    /// been published. See the [signing] module for details, and
+    /// [`signing::load_signing_key`] for reading a key from a PEM file.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WaczError`] wrapping a [`SigningError`]
+    /// if the public key cannot be encoded.
+    #[cfg(feature = "signing")]
+    pub fn sign(&mut self, signing_key: &SigningKey) -> Result<(), WaczError> {
+        match self.datapackage_digest.sign(signing_key) {
+            Ok(()) => return Ok(()),
+            Err(signing_error) => return Err(WaczError::SigningError(signing_error)),
+        }
+    }
+    /// # Verify WACZ signature
+    ///
+    /// Checks the signature on the datapackage digest against the public
+    /// key included with it, and checks that `datapackage.json` still
+    /// matches the signed hash. For a WACZ opened from an archive, this
+    /// hashes the `datapackage.json` file exactly as it was read.
+    ///
+    /// This does not check the resources listed in the datapackage, such
+    /// as the WARC files and indexes. Use [`WACZ::validate`] to check
+    /// those against their hashes.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WaczError`] wrapping a [`SigningError`] if the WACZ
+    /// is not signed, if the signature does not match, or if the
+    /// datapackage does not match the signed hash.
+    #[cfg(feature = "signing")]
+    pub fn verify_signature(&self) -> Result<(), WaczError> {
+        if let Err(signing_error) = self.datapackage_digest.verify() {
+            return Err(WaczError::SigningError(signing_error));
+        }
+        let datapackage_bytes = match self.datapackage_json() {
+            Ok((datapackage_bytes, _)) => datapackage_bytes,
+            Err(serde_error) => {
+                return Err(WaczError::DataPackageError(
+                    DataPackageError::SerialisationError(serde_error),
+                ));
+            }
+        };
+        let datapackage_hash = DataPackageDigest::new(&datapackage_bytes).hash;
+        if datapackage_hash != self.datapackage_digest.hash {
+            return Err(WaczError::SigningError(SigningError::ContentMismatch {
+                expected: self.datapackage_digest.hash.clone(),
+                found: datapackage_hash,
+            }));
+        }
+        return Ok(());
+    }
+    /// # Validate WACZ file
+    ///
+    /// Checks an existing WACZ file on disk without loading it into a
+    /// [WACZ] struct, and returns a [`ValidationReport`] listing every
+    /// problem found. See the [validator] module for the checks made.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WaczError`] wrapping a [`ReaderError`] only if the
+    /// file cannot be opened or is not a zip archive at all. A WACZ
+    /// which fails any of the checks still returns a report.
+    pub fn validate(wacz_file_path: &Path) -> Result<ValidationReport, WaczError> {
+        let wacz_file = match File::open(wacz_file_path) {
+            Ok(wacz_file) => wacz_file,
+            Err(io_error) => {
+                return Err(WaczError::ReaderError(ReaderError::FileReadError(io_error)));
+            }
+        };
+        let mut buffer = vec![0_u8; rawzip::RECOMMENDED_BUFFER_SIZE];
+        match ZipArchive::from_file(wacz_file, &mut buffer) {
+            Ok(archive) => match validator::validate_archive(&archive) {
+                Ok(validation_report) => return Ok(validation_report),
+                Err(reader_error) => return Err(WaczError::ReaderError(reader_error)),
+            },
+            Err(zip_error) => {
+                return Err(WaczError::ReaderError(ReaderError::ZipError(zip_error)));
+            }
+        }
+    }
+    /// # Fetch archived response
+    ///
+    /// Takes a record from the CDX(J) index, usually found with a
+    /// [query](indexer::CDXJQuery), and reads the WARC record it points
+    /// to out of the WACZ. See the [replay] module for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WaczError`] wrapping a [`ReplayError`] if the WARC file
+    /// is missing, the record cannot be read from the location in the
+    /// index, or it does not contain an HTTP response.
+    pub fn fetch_record(
+        &self,
+        cdxj_record: &CDXJIndexRecord,
+    ) -> Result<ArchivedResponse, WaczError> {
+        match replay::fetch_record(&self.datapackage, cdxj_record) {
+            Ok(archived_response) => return Ok(archived_response),
+            Err(replay_error) => return Err(WaczError::ReplayError(replay_error)),
         }
     }
     /// # Zipper
     ///
-    /// Takes a WACZ struct and zips up every element into a zip file.
-    /// This function is mostly a wrapper around [rawzip](https://crates.io/crates/rawzip).
+    /// Takes a WACZ struct and zips up every element into a zip file
+    /// held in memory. This is a convenience wrapper around [`WACZ::write_to`],
+    /// for large WARC files it is better to write straight to a file.
     ///
     /// # Errors
     ///
-    /// Returns a `rawzip` error if anything goes wrong with adding files
-    /// files to the archive.
-    pub fn zip(&self) -> Result<Vec<u8>, rawzip::Error> {
-        fn add_file_to_archive(
-            archive: &mut ZipArchiveWriter<&mut Vec<u8>>,
-            compression_method: CompressionMethod,
-            file_data: &[u8],
-            file_path: &str,
-        ) {
-            // Start a new file in our zip archive.
-            let mut file = archive
-                .new_file(file_path)
-                .compression_method(compression_method)
-                .create()
-                .unwrap();
-
-            // Wrap the file in a ZipDataWriter, which will track information for the
-            // Zip data descriptor (like uncompressed size and crc).
-            let mut writer = ZipDataWriter::new(&mut file);
-
-            // Copy the data to the writer.
-            std::io::copy(&mut &*file_data, &mut writer).unwrap();
-
-            // Finish the file, which will return the finalized data descriptor
-            let (_, descriptor) = writer.finish().unwrap();
-
-            let uncompressed_size = descriptor.uncompressed_size();
-
-            println!("wrote {uncompressed_size} bytes to {file_path}");
-
-            // Write out the data descriptor and return the number of bytes the data compressed to.
-            file.finish(descriptor).unwrap();
+    /// Returns a [`WriterError`](WaczError::WriterError) if anything goes
+    /// wrong with adding files to the archive, see [`WACZ::write_to`].
+    pub fn zip(&self) -> Result<Vec<u8>, WaczError> {
+        return self.write_to(Vec::new());
+    }
+    /// # Write WACZ
+    ///
+    /// Takes a WACZ struct and streams every element into a zip file,
+    /// written out to anything which implements `Write`. WARC files are
+    /// copied from disk and hashed as they go, so memory use stays the
+    /// same however large the WARC file is. See the [writer] module.
+    ///
+    /// Every file in the archive is timestamped with the `created` time
+    /// from the datapackage, so the output only depends on the contents
+    /// of the WACZ struct.
+    ///
+    /// Returns the writer once the archive is finished.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WriterError`](WaczError::WriterError) if anything goes
+    /// wrong with adding files to the archive, if a WARC file cannot be
+    /// read or has changed on disk since the datapackage was created, or
+    /// if the datapackage cannot be serialised to json.
+    pub fn write_to<Writer: Write>(&self, writer: Writer) -> Result<Writer, WaczError> {
+        match writer::write_archive(self, writer) {
+            Ok(writer) => return Ok(writer),
+            Err(writer_error) => return Err(WaczError::WriterError(writer_error)),
         }
-
-        // Create a new Zip archive in memory.
-        let mut output = Vec::new();
-        let mut archive = ZipArchiveWriter::new(&mut output);
-
-        // Set compression method to Store (no compression).
-        let compression_method = CompressionMethod::Store;
-
-        // iterate over every resource in the datapackage
-        for datapackage_resource in &self.datapackage.resources {
-            add_file_to_archive(
-                &mut archive,
-                compression_method,
-                &datapackage_resource.content,
-                &datapackage_resource.path,
-            );
+    }
+    /// # Datapackage file
+    ///
+    /// Returns the bytes of the `datapackage.json` file to write out,
+    /// along with its digest.
+    ///
+    /// A datapackage read from an archive is written back exactly as it
+    /// was read, as long as it has not been changed, so any fields this
+    /// library does not know about are kept, and it still matches its
+    /// digest and signature. Otherwise the datapackage is serialised, and
+    /// if that does not match the digest, a new digest is made. The
+    /// signature would no longer be valid, so it is left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `serde_json` error if the datapackage cannot be serialised.
+    pub(crate) fn datapackage_json(
+        &self,
+    ) -> Result<(Cow<'_, [u8]>, Cow<'_, DataPackageDigest>), serde_json::Error> {
+        let datapackage_bytes = serde_json::to_vec(&self.datapackage)?;
+        if let Some(datapackage_file) = &self.datapackage_file {
+            if datapackage_file.serialised == datapackage_bytes {
+                return Ok((
+                    Cow::Borrowed(&datapackage_file.bytes),
+                    Cow::Borrowed(&self.datapackage_digest),
+                ));
+            }
         }
-
-        // add datapackage file
-        add_file_to_archive(
-            &mut archive,
-            compression_method,
-            &serde_json::to_vec(&self.datapackage).unwrap(),
-            "datapackage.json",
-        );
-
-        // add digest file
-        add_file_to_archive(
-            &mut archive,
-            compression_method,
-            &serde_json::to_vec(&self.datapackage_digest).unwrap(),
-            "datapackage-digest.json",
-        );
-
-        // Finish the archive, which will write the central directory.
-        archive.finish()?;
-
-        return Ok(output);
+        let datapackage_digest = DataPackageDigest::new(&datapackage_bytes);
+        if datapackage_digest.hash == self.datapackage_digest.hash {
+            return Ok((
+                Cow::Owned(datapackage_bytes),
+                Cow::Borrowed(&self.datapackage_digest),
+            ));
+        }
+        return Ok((
+            Cow::Owned(datapackage_bytes),
+            Cow::Owned(datapackage_digest),
+        ));
     }
 }
 
+/// An error from creating, reading, or signing a WACZ.
+///
+/// Some variants only exist with optional features enabled, such as
+/// `signing`, so this cannot be matched exhaustively.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum WaczError {
     IndexingError(IndexingError),
     DataPackageError(DataPackageError),
+    ReaderError(ReaderError),
+    ReplayError(ReplayError),
+    WriterError(WriterError),
+    #[cfg(feature = "signing")]
+    SigningError(SigningError),
 }
 impl fmt::Display for WaczError {
     fn fmt(&self, message: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -170,6 +398,19 @@ impl fmt::Display for WaczError {
             Self::DataPackageError(error_message) => {
                 return write!(message, "Error when creating datapackage: {error_message}");
             }
+            Self::ReaderError(error_message) => {
+                return write!(message, "Error when reading WACZ: {error_message}");
+            }
+            Self::ReplayError(error_message) => {
+                return write!(message, "Error when fetching record: {error_message}");
+            }
+            Self::WriterError(error_message) => {
+                return write!(message, "Error when writing WACZ: {error_message}");
+            }
+            #[cfg(feature = "signing")]
+            Self::SigningError(error_message) => {
+                return write!(message, "Signing error: {error_message}");
+            }
         }
     }
 }
@@ -178,6 +419,11 @@ impl Error for WaczError {
         match self {
             Self::IndexingError(error) => return Some(error),
             Self::DataPackageError(error) => return Some(error),
+            Self::ReaderError(error) => return Some(error),
+            Self::ReplayError(error) => return Some(error),
+            Self::WriterError(error) => return Some(error),
+            #[cfg(feature = "signing")]
+            Self::SigningError(error) => return Some(error),
         }
     }
 }
@@ -0,0 +1,310 @@
+//! The `wacksy` command-line tool, built with the `cli` feature.
+//!
+//! ```text
+//! wacksy create crawl-0.warc.gz crawl-1.warc.gz --output crawl.wacz
+//! wacksy index crawl-0.warc.gz > index.cdxj
+//! wacksy validate crawl.wacz
+//! wacksy inspect crawl.wacz
+//! ```
+//!
+//! The exit code is `0` on success, `2` if the arguments are wrong, and
+//! otherwise depends on what went wrong, see [`exit_code`].
+
+use clap::{Parser, Subcommand, ValueEnum};
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{self, BufWriter, Write as _},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+use wacksy::{
+    WACZ, WaczError,
+    indexer::{ErrorPolicy, Index, IndexFormat, IndexOptions, ZIPNUM_LINES_PER_BLOCK},
+};
+
+/// Create, index, validate and inspect WACZ files.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Index one or more WARC files and package them into a WACZ.
+    Create {
+        /// The WARC files to add to the WACZ
+        #[arg(required = true)]
+        warc_files: Vec<PathBuf>,
+        /// Where to write the WACZ file
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Write a compressed `ZipNum` index instead of a plain CDXJ index
+        #[arg(long)]
+        zipnum: bool,
+        /// The number of lines in each block of a `ZipNum` index
+        #[arg(long, default_value_t = ZIPNUM_LINES_PER_BLOCK, requires = "zipnum")]
+        lines_per_block: usize,
+        /// What to do with records which cannot be indexed
+        #[arg(long, value_enum, default_value_t = Policy::Lenient)]
+        error_policy: Policy,
+        /// Find the original capture of each revisit record
+        #[arg(long)]
+        resolve_revisits: bool,
+    },
+    /// Index one or more WARC files and print the index to stdout.
+    Index {
+        /// The WARC files to index
+        #[arg(required = true)]
+        warc_files: Vec<PathBuf>,
+        /// Print the pages.jsonl list instead of the CDXJ index
+        #[arg(long)]
+        pages: bool,
+        /// Print every record skipped by the indexer to stderr
+        #[arg(short, long)]
+        verbose: bool,
+        /// What to do with records which cannot be indexed
+        #[arg(long, value_enum, default_value_t = Policy::Lenient)]
+        error_policy: Policy,
+        /// Find the original capture of each revisit record
+        #[arg(long)]
+        resolve_revisits: bool,
+    },
+    /// Check the structure, hashes and index of a WACZ file.
+    Validate {
+        /// The WACZ file to validate
+        wacz_file: PathBuf,
+    },
+    /// Print a summary of the datapackage in a WACZ file.
+    Inspect {
+        /// The WACZ file to inspect
+        wacz_file: PathBuf,
+    },
+}
+
+/// The indexer [error policy](ErrorPolicy), as a command-line option.
+#[derive(Clone, Copy, ValueEnum)]
+enum Policy {
+    /// Stop on the first record which cannot be indexed
+    Strict,
+    /// Skip records which cannot be indexed
+    Lenient,
+    /// Skip records which cannot be indexed, and skip
+    /// over damaged records to the next one
+    Resync,
+}
+impl From<Policy> for ErrorPolicy {
+    fn from(policy: Policy) -> Self {
+        match policy {
+            Policy::Strict => return Self::Strict,
+            Policy::Lenient => return Self::Lenient,
+            Policy::Resync => return Self::Resync,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Create {
+            warc_files,
+            output,
+            zipnum,
+            lines_per_block,
+            error_policy,
+            resolve_revisits,
+        } => {
+            let index_format = if zipnum {
+                IndexFormat::ZipNum { lines_per_block }
+            } else {
+                IndexFormat::Cdxj
+            };
+            let index_options = IndexOptions {
+                error_policy: error_policy.into(),
+                resolve_revisits,
+                ..IndexOptions::default()
+            };
+            create(&warc_files, &output, index_format, &index_options)
+        }
+        Command::Index {
+            warc_files,
+            pages,
+            verbose,
+            error_policy,
+            resolve_revisits,
+        } => {
+            let index_options = IndexOptions {
+                error_policy: error_policy.into(),
+                resolve_revisits,
+                ..IndexOptions::default()
+            };
+            index(&warc_files, pages, verbose, &index_options)
+        }
+        Command::Validate { wacz_file } => validate(&wacz_file),
+        Command::Inspect { wacz_file } => inspect(&wacz_file),
+    };
+    match result {
+        Ok(()) => return ExitCode::SUCCESS,
+        Err(cli_error) => {
+            eprintln!("wacksy: {cli_error}");
+            return ExitCode::from(exit_code(&cli_error));
+        }
+    }
+}
+
+/// Anything which can go wrong when running a command.
+#[derive(Debug)]
+enum CliError {
+    /// a problem creating, reading, writing or fetching from the WACZ
+    Wacz(WaczError),
+    /// a problem writing to stdout
+    Output(io::Error),
+    /// the WACZ file failed validation
+    Invalid(usize),
+}
+impl std::fmt::Display for CliError {
+    fn fmt(&self, message: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Wacz(error_message) => return write!(message, "{error_message}"),
+            Self::Output(error_message) => {
+                return write!(message, "Could not write output: {error_message}");
+            }
+            Self::Invalid(number_of_problems) => {
+                return write!(
+                    message,
+                    "WACZ is not valid, found {number_of_problems} problems"
+                );
+            }
+        }
+    }
+}
+
+/// # Exit code
+///
+/// Each kind of error has its own exit code, so scripts
+/// can tell what went wrong:
+///
+/// - `1` could not write the output, or any other error
+/// - `3` could not index the WARC files
+/// - `4` could not create the datapackage
+/// - `5` could not read the WACZ file
+/// - `6` could not fetch a record from the WACZ
+/// - `7` the WACZ file is not valid
+/// - `8` could not sign or verify the WACZ
+const fn exit_code(cli_error: &CliError) -> u8 {
+    match cli_error {
+        CliError::Output(_) | CliError::Wacz(WaczError::WriterError(_)) => return 1,
+        CliError::Wacz(WaczError::IndexingError(_)) => return 3,
+        CliError::Wacz(WaczError::DataPackageError(_)) => return 4,
+        CliError::Wacz(WaczError::ReaderError(_)) => return 5,
+        CliError::Wacz(WaczError::ReplayError(_)) => return 6,
+        CliError::Invalid(_) => return 7,
+        #[cfg(feature = "signing")]
+        CliError::Wacz(WaczError::SigningError(_)) => return 8,
+        // any errors added to the library later
+        CliError::Wacz(_) => return 1,
+    }
+}
+
+fn create(
+    warc_files: &[PathBuf],
+    output: &Path,
+    index_format: IndexFormat,
+    index_options: &IndexOptions,
+) -> Result<(), CliError> {
+    let wacz_build = WACZ::builder()
+        .warc_files(warc_files)
+        .index_format(index_format)
+        .error_policy(index_options.error_policy)
+        .resolve_revisits(index_options.resolve_revisits)
+        .build();
+    let wacz_object = match wacz_build {
+        Ok(wacz_object) => wacz_object,
+        Err(wacz_error) => return Err(CliError::Wacz(wacz_error)),
+    };
+    let wacz_file = match File::create(output) {
+        Ok(wacz_file) => wacz_file,
+        Err(io_error) => return Err(CliError::Output(io_error)),
+    };
+    match wacz_object.write_to(BufWriter::new(wacz_file)) {
+        Ok(mut writer) => match writer.flush() {
+            Ok(()) => return Ok(()),
+            Err(io_error) => return Err(CliError::Output(io_error)),
+        },
+        Err(wacz_error) => return Err(CliError::Wacz(wacz_error)),
+    }
+}
+
+fn index(
+    warc_files: &[PathBuf],
+    pages: bool,
+    verbose: bool,
+    index_options: &IndexOptions,
+) -> Result<(), CliError> {
+    let index = match Index::index_files_with_options(warc_files, index_options) {
+        Ok(index) => index,
+        Err(indexing_error) => {
+            return Err(CliError::Wacz(WaczError::IndexingError(indexing_error)));
+        }
+    };
+    if verbose {
+        for indexing_warning in &index.warnings {
+            eprintln!("wacksy: {indexing_warning}");
+        }
+    }
+    let written = if pages {
+        write!(io::stdout().lock(), "{}", index.pages)
+    } else {
+        write!(io::stdout().lock(), "{}", index.cdxj)
+    };
+    match written {
+        Ok(()) => return Ok(()),
+        Err(io_error) => return Err(CliError::Output(io_error)),
+    }
+}
+
+fn validate(wacz_file: &Path) -> Result<(), CliError> {
+    let validation_report = match WACZ::validate(wacz_file) {
+        Ok(validation_report) => validation_report,
+        Err(wacz_error) => return Err(CliError::Wacz(wacz_error)),
+    };
+    if let Err(io_error) = write!(io::stdout().lock(), "{validation_report}") {
+        return Err(CliError::Output(io_error));
+    }
+    if validation_report.is_valid() {
+        return Ok(());
+    } else {
+        return Err(CliError::Invalid(validation_report.problems.len()));
+    }
+}
+
+fn inspect(wacz_file: &Path) -> Result<(), CliError> {
+    // this reads the datapackage, index and pages list, the
+    // WARC files are left in the archive as nothing reads them
+    let wacz_object = match WACZ::open(wacz_file) {
+        Ok(wacz_object) => wacz_object,
+        Err(wacz_error) => return Err(CliError::Wacz(wacz_error)),
+    };
+    let datapackage = &wacz_object.datapackage;
+    let mut summary = format!(
+        "WACZ version: {}\ncreated: {}\nsoftware: {}\nrecords: {}\npages: {}\nresources:\n",
+        datapackage.wacz_version,
+        datapackage.created,
+        datapackage.software,
+        wacz_object.cdxj_index.len(),
+        wacz_object.pages_index.len(),
+    );
+    for datapackage_resource in &datapackage.resources {
+        let _ = writeln!(
+            summary,
+            "  {} ({} bytes, {})",
+            datapackage_resource.path, datapackage_resource.bytes, datapackage_resource.hash
+        );
+    }
+    match write!(io::stdout().lock(), "{summary}") {
+        Ok(()) => return Ok(()),
+        Err(io_error) => return Err(CliError::Output(io_error)),
+    }
+}
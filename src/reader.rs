@@ -0,0 +1,410 @@
+//! Reads an existing WACZ file back into a [`WACZ`] struct.
+//!
+//! Only the parts of the archive listed in the [spec](https://specs.webrecorder.net/wacz/1.1.1/#directory-layout)
+//! are read, these are the `datapackage.json` and `datapackage-digest.json`
+//! files, the CDXJ index at `indexes/index.cdxj` (or the compressed index
+//! at `indexes/index.cdx.gz`) and the pages list at `pages/pages.jsonl`.
+//!
+//! The other resources listed in the datapackage, such as WARC files, can
+//! be very large, so they are not loaded into memory. Each one is kept as
+//! a [`ZipEntryContent`], which reads the file from the zip archive when
+//! it is needed, so the WACZ can still be zipped up again.
+
+use libflate::{deflate, gzip};
+use rawzip::{CompressionMethod, ReaderAt, ZipArchive, ZipArchiveEntryWayfinder};
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read},
+    str::FromStr as _,
+    sync::Arc,
+};
+
+use crate::{
+    WACZ,
+    builder::ZipCompression,
+    datapackage::{DataPackage, DataPackageDigest, ResourceContent},
+    indexer::{CDXJIndex, IndexingError, PageIndex},
+};
+
+/// The location of a file inside the zip archive.
+#[derive(Clone)]
+pub(crate) struct ArchiveEntry {
+    pub(crate) path: String,
+    wayfinder: ZipArchiveEntryWayfinder,
+    compression_method: CompressionMethod,
+}
+
+/// # Read archive
+///
+/// Reads the central directory of the zip archive, then reads and
+/// parses the datapackage, indexes and pages list. The archive is
+/// kept open, so the other resources can be read from it later.
+///
+/// # Errors
+///
+/// Returns a [`ReaderError`] if the zip cannot be read, if any of the
+/// required files are missing, or if any file cannot be parsed.
+pub(crate) fn read_archive<Reader: ReaderAt + Send + Sync + 'static>(
+    archive: ZipArchive<Reader>,
+) -> Result<WACZ, ReaderError> {
+    let archive_handle = Arc::new(archive);
+    let archive = archive_handle.as_ref();
+    let archive_entries = list_entries(archive)?;
+
+    let datapackage_bytes = read_entry(archive, &archive_entries, "datapackage.json")?;
+    let mut datapackage: DataPackage = serde_json::from_slice(&datapackage_bytes)?;
+    let datapackage_file = DataPackageFile {
+        serialised: serde_json::to_vec(&datapackage)?,
+        bytes: datapackage_bytes,
+    };
+    let datapackage_digest: DataPackageDigest = serde_json::from_slice(&read_entry(
+        archive,
+        &archive_entries,
+        "datapackage-digest.json",
+    )?)?;
+    // the index can either be plain text, or a compressed ZipNum index
+    let cdxj_index = if archive_entries
+        .iter()
+        .any(|archive_entry| return archive_entry.path == "indexes/index.cdxj")
+    {
+        CDXJIndex::from_str(&read_entry_to_string(
+            archive,
+            &archive_entries,
+            "indexes/index.cdxj",
+        )?)?
+    } else {
+        let compressed_index = read_entry(archive, &archive_entries, "indexes/index.cdx.gz")?;
+        let mut decompressed_index = String::new();
+        gzip::MultiDecoder::new(compressed_index.as_slice())?
+            .read_to_string(&mut decompressed_index)?;
+        CDXJIndex::from_str(&decompressed_index)?
+    };
+    let pages_index = PageIndex::from_str(&read_entry_to_string(
+        archive,
+        &archive_entries,
+        "pages/pages.jsonl",
+    )?)?;
+
+    // the content of each resource is not stored in the datapackage.json
+    // file itself, so point each one at its file in the archive
+    let zip_source: Arc<dyn ZipSource> = archive_handle;
+    for datapackage_resource in &mut datapackage.resources {
+        let Some(archive_entry) = archive_entries
+            .iter()
+            .find(|archive_entry| return archive_entry.path == datapackage_resource.path)
+        else {
+            return Err(ReaderError::MissingFile(datapackage_resource.path.clone()));
+        };
+        datapackage_resource.content = ResourceContent::ZipEntry(ZipEntryContent {
+            archive: Arc::clone(&zip_source),
+            archive_entry: archive_entry.clone(),
+        });
+    }
+
+    return Ok(WACZ {
+        datapackage,
+        datapackage_digest,
+        cdxj_index,
+        pages_index,
+        compression: ZipCompression::default(),
+        datapackage_file: Some(datapackage_file),
+    });
+}
+
+/// The `datapackage.json` file of an archive which has been read.
+pub(crate) struct DataPackageFile {
+    /// The file exactly as it is in the archive
+    pub(crate) bytes: Vec<u8>,
+    /// The datapackage as it was read, serialised again,
+    /// to tell whether it has been changed since
+    pub(crate) serialised: Vec<u8>,
+}
+
+/// Read the central directory of the zip archive,
+/// listing every file in it along with its location.
+pub(crate) fn list_entries<Reader: ReaderAt>(
+    archive: &ZipArchive<Reader>,
+) -> Result<Vec<ArchiveEntry>, ReaderError> {
+    let mut buffer = vec![0_u8; rawzip::RECOMMENDED_BUFFER_SIZE];
+    let mut archive_entries: Vec<ArchiveEntry> = Vec::with_capacity(8);
+    let mut entries = archive.entries(&mut buffer);
+    while let Some(entry) = entries.next_entry()? {
+        if entry.is_dir() {
+            continue;
+        }
+        archive_entries.push(ArchiveEntry {
+            path: String::from(entry.file_path().try_normalize()?),
+            wayfinder: entry.wayfinder(),
+            compression_method: entry.compression_method(),
+        });
+    }
+    return Ok(archive_entries);
+}
+
+/// Find a file by its path in the archive and return its
+/// decompressed contents, checking the size and CRC as it goes.
+pub(crate) fn read_entry<Reader: ReaderAt>(
+    archive: &ZipArchive<Reader>,
+    archive_entries: &[ArchiveEntry],
+    file_path: &str,
+) -> Result<Vec<u8>, ReaderError> {
+    let mut file_contents: Vec<u8> = Vec::new();
+    read_entry_with(archive, archive_entries, file_path, |entry_reader| {
+        return entry_reader.read_to_end(&mut file_contents);
+    })?;
+    return Ok(file_contents);
+}
+
+/// # Read entry with a function
+///
+/// Find a file by its path in the archive and pass a reader over its
+/// decompressed contents to `read_function`, so large files such as
+/// WARCs can be streamed rather than held in memory. The size and CRC
+/// are checked as the file is read.
+///
+/// # Errors
+///
+/// Returns a [`ReaderError`] if the file is missing or compressed
+/// with an unsupported method, or wraps any `io::Error` returned by
+/// `read_function`.
+pub(crate) fn read_entry_with<Reader: ReaderAt, Output>(
+    archive: &ZipArchive<Reader>,
+    archive_entries: &[ArchiveEntry],
+    file_path: &str,
+    read_function: impl FnOnce(&mut dyn Read) -> io::Result<Output>,
+) -> Result<Output, ReaderError> {
+    let Some(archive_entry) = archive_entries
+        .iter()
+        .find(|archive_entry| return archive_entry.path == file_path)
+    else {
+        return Err(ReaderError::MissingFile(file_path.to_owned()));
+    };
+
+    let zip_entry = archive.get_entry(archive_entry.wayfinder)?;
+    match archive_entry.compression_method {
+        CompressionMethod::Store => {
+            return Ok(read_function(
+                &mut zip_entry.verifying_reader(zip_entry.reader()),
+            )?);
+        }
+        CompressionMethod::Deflate => {
+            return Ok(read_function(
+                &mut zip_entry.verifying_reader(deflate::Decoder::new(zip_entry.reader())),
+            )?);
+        }
+        unsupported_method => {
+            return Err(ReaderError::UnsupportedCompression(
+                file_path.to_owned(),
+                unsupported_method,
+            ));
+        }
+    }
+}
+
+pub(crate) fn read_entry_to_string<Reader: ReaderAt>(
+    archive: &ZipArchive<Reader>,
+    archive_entries: &[ArchiveEntry],
+    file_path: &str,
+) -> Result<String, ReaderError> {
+    match String::from_utf8(read_entry(archive, archive_entries, file_path)?) {
+        Ok(file_contents) => return Ok(file_contents),
+        Err(utf8_error) => {
+            return Err(ReaderError::EncodingError(file_path.to_owned(), utf8_error));
+        }
+    }
+}
+
+/// A resource inside a WACZ which has been opened, such as a WARC
+/// file, which is read from the zip archive each time it is needed
+/// rather than being held in memory.
+#[derive(Clone)]
+pub struct ZipEntryContent {
+    archive: Arc<dyn ZipSource>,
+    archive_entry: ArchiveEntry,
+}
+impl ZipEntryContent {
+    /// # Open zip entry
+    ///
+    /// Returns a reader over the decompressed content of the file,
+    /// which checks the size and CRC as it goes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the file cannot be found in the
+    /// archive, or is compressed with an unsupported method.
+    pub fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        return self.archive.entry_reader(&self.archive_entry, 0);
+    }
+    /// # Open zip entry at an offset
+    ///
+    /// The same as [`ZipEntryContent::reader`], but starts `offset` bytes
+    /// into the decompressed content. Files stored without compression,
+    /// which WARC files in a WACZ usually are, are read straight from the
+    /// offset, otherwise everything before it is decompressed and skipped.
+    /// The CRC is not checked if only part of the file is read.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`ZipEntryContent::reader`].
+    pub fn reader_from(&self, offset: u64) -> io::Result<Box<dyn Read + '_>> {
+        return self.archive.entry_reader(&self.archive_entry, offset);
+    }
+}
+impl fmt::Debug for ZipEntryContent {
+    fn fmt(&self, message: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return message
+            .debug_struct("ZipEntryContent")
+            .field("path", &self.archive_entry.path)
+            .finish_non_exhaustive();
+    }
+}
+
+/// An open zip archive, whatever it is being read from.
+trait ZipSource: Send + Sync {
+    /// Returns a reader over the decompressed content of a file in
+    /// the archive, starting `offset` bytes into the file.
+    fn entry_reader(
+        &self,
+        archive_entry: &ArchiveEntry,
+        offset: u64,
+    ) -> io::Result<Box<dyn Read + '_>>;
+}
+impl<Reader: ReaderAt + Send + Sync> ZipSource for ZipArchive<Reader> {
+    fn entry_reader(
+        &self,
+        archive_entry: &ArchiveEntry,
+        offset: u64,
+    ) -> io::Result<Box<dyn Read + '_>> {
+        let zip_entry = match self.get_entry(archive_entry.wayfinder) {
+            Ok(zip_entry) => zip_entry,
+            Err(zip_error) => return Err(io::Error::other(zip_error)),
+        };
+        let mut entry_reader: Box<dyn Read + '_> = match archive_entry.compression_method {
+            // stored files can be read from any position,
+            // without reading everything before it
+            CompressionMethod::Store if offset > 0 => {
+                let (data_start, data_end) = zip_entry.compressed_data_range();
+                return Ok(Box::new(RangeReader {
+                    reader: self.get_ref(),
+                    position: data_start.saturating_add(offset).min(data_end),
+                    end: data_end,
+                }));
+            }
+            CompressionMethod::Store => Box::new(zip_entry.verifying_reader(zip_entry.reader())),
+            CompressionMethod::Deflate => {
+                Box::new(zip_entry.verifying_reader(deflate::Decoder::new(zip_entry.reader())))
+            }
+            unsupported_method => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    ReaderError::UnsupportedCompression(
+                        archive_entry.path.clone(),
+                        unsupported_method,
+                    )
+                    .to_string(),
+                ));
+            }
+        };
+        io::copy(&mut entry_reader.by_ref().take(offset), &mut io::sink())?;
+        return Ok(entry_reader);
+    }
+}
+
+/// Reads a range of bytes from a `ReaderAt`.
+struct RangeReader<'archive, Reader> {
+    reader: &'archive Reader,
+    position: u64,
+    end: u64,
+}
+impl<Reader: ReaderAt> Read for RangeReader<'_, Reader> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let remaining_bytes = usize::try_from(self.end - self.position).unwrap_or(usize::MAX);
+        let read_length = buffer.len().min(remaining_bytes);
+        let bytes_read = self
+            .reader
+            .read_at(&mut buffer[..read_length], self.position)?;
+        self.position += bytes_read as u64;
+        return Ok(bytes_read);
+    }
+}
+
+#[derive(Debug)]
+pub enum ReaderError {
+    /// could not open the WACZ file
+    FileReadError(io::Error),
+    /// problem with the structure of the zip archive
+    ZipError(rawzip::Error),
+    /// a file required by the spec is not in the archive
+    MissingFile(String),
+    /// the file is compressed with something other than store or deflate
+    UnsupportedCompression(String, CompressionMethod),
+    /// the file is not valid UTF-8
+    EncodingError(String, std::string::FromUtf8Error),
+    /// could not deserialise one of the json files
+    SerialisationError(serde_json::Error),
+    /// could not parse one of the indexes
+    IndexError(IndexingError),
+}
+impl fmt::Display for ReaderError {
+    fn fmt(&self, message: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FileReadError(error_message) => {
+                return write!(message, "Could not read WACZ file: {error_message}");
+            }
+            Self::ZipError(error_message) => {
+                return write!(message, "Could not read zip archive: {error_message}");
+            }
+            Self::MissingFile(file_path) => {
+                return write!(message, "File missing from WACZ: {file_path}");
+            }
+            Self::UnsupportedCompression(file_path, compression_method) => {
+                return write!(
+                    message,
+                    "Unsupported compression method {compression_method:?} for {file_path}"
+                );
+            }
+            Self::EncodingError(file_path, error_message) => {
+                return write!(message, "Could not decode {file_path}: {error_message}");
+            }
+            Self::SerialisationError(error_message) => {
+                return write!(message, "Serialisation error: {error_message}");
+            }
+            Self::IndexError(error_message) => {
+                return write!(message, "Index error: {error_message}");
+            }
+        }
+    }
+}
+impl Error for ReaderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::FileReadError(io_error) => return Some(io_error),
+            Self::ZipError(zip_error) => return Some(zip_error),
+            Self::EncodingError(_, utf8_error) => return Some(utf8_error),
+            Self::SerialisationError(serde_error) => return Some(serde_error),
+            Self::IndexError(indexing_error) => return Some(indexing_error),
+            Self::MissingFile(_) | Self::UnsupportedCompression(..) => return None,
+        }
+    }
+}
+impl From<rawzip::Error> for ReaderError {
+    fn from(zip_error: rawzip::Error) -> Self {
+        return Self::ZipError(zip_error);
+    }
+}
+impl From<io::Error> for ReaderError {
+    fn from(io_error: io::Error) -> Self {
+        return Self::FileReadError(io_error);
+    }
+}
+impl From<serde_json::Error> for ReaderError {
+    fn from(serde_error: serde_json::Error) -> Self {
+        return Self::SerialisationError(serde_error);
+    }
+}
+impl From<IndexingError> for ReaderError {
+    fn from(indexing_error: IndexingError) -> Self {
+        return Self::IndexError(indexing_error);
+    }
+}
@@ -0,0 +1,218 @@
+//! Fetches archived records out of a WACZ using the CDX(J) index.
+//!
+//! Each [CDX(J) record](CDXJIndexRecord) gives the WARC file, offset and
+//! length of a WARC record. The WARC file is found in the `archive/`
+//! directory of the [datapackage](DataPackage), and the bytes at that
+//! location are decompressed if needed and parsed back into a WARC record,
+//! along with the HTTP response it contains.
+
+use libflate::gzip;
+use std::{error::Error, fmt, io, io::Read as _};
+use warc::{BufferedBody, Record, WarcReader};
+
+use crate::{
+    datapackage::DataPackage,
+    indexer::{CDXJIndexRecord, HttpResponseHead, RecordContentType},
+};
+
+/// The magic bytes at the start of every gzip member.
+const GZIP_MAGIC_BYTES: [u8; 2] = [0x1f, 0x8b];
+
+/// A WARC record fetched from a WACZ, with
+/// the HTTP response inside it parsed out.
+pub struct ArchivedResponse {
+    /// The full WARC record, including the WARC headers
+    pub record: Record<BufferedBody>,
+    /// The HTTP status code of the response
+    pub status: u16,
+    /// The HTTP response headers, in the order they were archived
+    pub headers: Vec<(String, Vec<u8>)>,
+    /// The payload of the HTTP response, exactly as it was archived,
+    /// so any transfer or content encoding is left in place
+    pub body: Vec<u8>,
+}
+impl ArchivedResponse {
+    /// # Get HTTP header
+    ///
+    /// Returns the value of the first HTTP header with this
+    /// name, ignoring case, or `None` if it is not present.
+    #[must_use]
+    pub fn header(&self, header_name: &str) -> Option<&[u8]> {
+        return self
+            .headers
+            .iter()
+            .find(|(name, _)| return name.eq_ignore_ascii_case(header_name))
+            .map(|(_, value)| return value.as_slice());
+    }
+}
+
+/// # Fetch record
+///
+/// Finds the WARC file named in the CDX(J) record, reads `length` bytes
+/// from `offset`, and parses them into an [`ArchivedResponse`]. Both
+/// gzipped and uncompressed WARC files are supported.
+///
+/// # Errors
+///
+/// Returns a [`ReplayError`] if the WARC file is not in the datapackage,
+/// if the bytes at the location in the index cannot be read or are not
+/// a WARC record, or if the record does not contain an HTTP response,
+/// such as a `warc/request` line in the index.
+pub(crate) fn fetch_record(
+    datapackage: &DataPackage,
+    cdxj_record: &CDXJIndexRecord,
+) -> Result<ArchivedResponse, ReplayError> {
+    if cdxj_record
+        .mime
+        .as_ref()
+        .is_some_and(|mime| return mime.mime == RecordContentType::REQUEST)
+    {
+        return Err(ReplayError::RequestRecord(cdxj_record.url.to_string()));
+    }
+    let warc_file_path = format!("archive/{}", cdxj_record.filename);
+    let Some(warc_file) = datapackage
+        .resources
+        .iter()
+        .find(|datapackage_resource| return datapackage_resource.path == warc_file_path)
+    else {
+        return Err(ReplayError::MissingWarcFile(warc_file_path));
+    };
+
+    // seek to the offset and read only the bytes for this record
+    let warc_reader = warc_file.content.reader_from(cdxj_record.offset)?;
+    // the length comes from the index, so it is not trusted
+    // to say how much memory to set aside
+    let mut record_bytes: Vec<u8> = Vec::new();
+    warc_reader
+        .take(cdxj_record.length)
+        .read_to_end(&mut record_bytes)?;
+    if (record_bytes.len() as u64) < cdxj_record.length {
+        return Err(ReplayError::RecordNotFound(format!(
+            "{warc_file_path} is too short to contain {} bytes at offset {}",
+            cdxj_record.length, cdxj_record.offset
+        )));
+    }
+
+    let record = match parse_warc_record(&record_bytes) {
+        Ok(record) => record,
+        Err(ReplayError::RecordNotFound(_)) => {
+            return Err(ReplayError::RecordNotFound(format!(
+                "no WARC record at offset {} in {warc_file_path}",
+                cdxj_record.offset
+            )));
+        }
+        Err(replay_error) => return Err(replay_error),
+    };
+
+    let response_head = match HttpResponseHead::parse(record.body()) {
+        Ok(response_head) => response_head,
+        Err(http_parsing_error) => {
+            return Err(ReplayError::HttpParsingError(
+                http_parsing_error.to_string(),
+            ));
+        }
+    };
+    let Some(status) = response_head.status else {
+        return Err(ReplayError::HttpParsingError(
+            "response code is empty".to_owned(),
+        ));
+    };
+    let headers = response_head.headers;
+    let body = record.body()[response_head.body_offset..].to_vec();
+
+    return Ok(ArchivedResponse {
+        record,
+        status,
+        headers,
+        body,
+    });
+}
+
+/// # Parse WARC record
+///
+/// Parses the bytes read from the location given in a CDX(J) record,
+/// decompressing them first if they are a gzip member, and returns the
+/// first WARC record.
+///
+/// # Errors
+///
+/// Returns a [`ReplayError`] if the bytes cannot be decompressed, or do
+/// not start with a valid WARC record.
+pub(crate) fn parse_warc_record(record_bytes: &[u8]) -> Result<Record<BufferedBody>, ReplayError> {
+    let mut decompressed_bytes: Vec<u8> = Vec::new();
+    let record_bytes = if record_bytes.starts_with(&GZIP_MAGIC_BYTES) {
+        gzip::Decoder::new(record_bytes)?.read_to_end(&mut decompressed_bytes)?;
+        decompressed_bytes.as_slice()
+    } else {
+        record_bytes
+    };
+
+    match WarcReader::new(record_bytes).iter_records().next() {
+        Some(Ok(record)) => return Ok(record),
+        Some(Err(warc_error)) => return Err(ReplayError::WarcError(warc_error)),
+        None => {
+            return Err(ReplayError::RecordNotFound(
+                "no WARC record found".to_owned(),
+            ));
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    /// the WARC file in the index is not in the datapackage
+    MissingWarcFile(String),
+    /// could not read the WARC file
+    FileReadError(io::Error),
+    /// nothing at the location given in the index
+    RecordNotFound(String),
+    /// could not parse the WARC record
+    WarcError(warc::Error),
+    /// could not parse the HTTP response in the record
+    HttpParsingError(String),
+    /// the record in the index is a request, which has no response
+    RequestRecord(String),
+}
+impl fmt::Display for ReplayError {
+    fn fmt(&self, message: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingWarcFile(file_path) => {
+                return write!(message, "WARC file missing from WACZ: {file_path}");
+            }
+            Self::FileReadError(error_message) => {
+                return write!(message, "Could not read WARC file: {error_message}");
+            }
+            Self::RecordNotFound(error_message) => {
+                return write!(message, "Record not found: {error_message}");
+            }
+            Self::WarcError(error_message) => {
+                return write!(message, "Could not parse WARC record: {error_message}");
+            }
+            Self::HttpParsingError(error_message) => {
+                return write!(message, "Could not parse HTTP response: {error_message}");
+            }
+            Self::RequestRecord(url) => {
+                return write!(message, "No response to replay for request record: {url}");
+            }
+        }
+    }
+}
+impl Error for ReplayError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::FileReadError(io_error) => return Some(io_error),
+            Self::WarcError(warc_error) => return Some(warc_error),
+            Self::MissingWarcFile(_)
+            | Self::RecordNotFound(_)
+            | Self::HttpParsingError(_)
+            | Self::RequestRecord(_) => {
+                return None;
+            }
+        }
+    }
+}
+impl From<io::Error> for ReplayError {
+    fn from(io_error: io::Error) -> Self {
+        return Self::FileReadError(io_error);
+    }
+}
@@ -0,0 +1,248 @@
+//! Signs and verifies WACZ files, built with the `signing` feature.
+//!
+//! This follows the anonymous mode of the [WACZ Auth spec](https://specs.webrecorder.net/wacz-auth/0.1.0/).
+//! The hash in `datapackage-digest.json` is signed with a local ECDSA
+//! P-256 key, and the signature is stored in a [`SignedData`] block
+//! alongside the public key, so anyone can check the WACZ has not been
+//! changed since it was signed:
+//!
+//! ```json
+//! {
+//!   "path": "datapackage.json",
+//!   "hash": "sha256:…",
+//!   "signedData": {
+//!     "hash": "sha256:…",
+//!     "signature": "MEUCIQ…",
+//!     "publicKey": "MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAE…",
+//!     "created": "2025-04-23T12:10:42.000Z",
+//!     "software": "wacksy 0.0.2"
+//!   }
+//! }
+//! ```
+//!
+//! Domain mode signatures, which rely on certificates rather than
+//! a bare public key, are read and written but cannot be verified.
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chrono::{SecondsFormat, Utc};
+use p256::{
+    SecretKey,
+    ecdsa::{
+        Signature, VerifyingKey,
+        signature::{Signer as _, Verifier as _},
+    },
+    pkcs8::{DecodePrivateKey as _, DecodePublicKey as _, EncodePublicKey as _},
+};
+use std::{error::Error, fmt};
+
+use crate::datapackage::{DataPackageDigest, SignedData, current_time};
+
+pub use p256::ecdsa::SigningKey;
+
+/// # Load signing key
+///
+/// Reads an ECDSA P-256 private key from a PEM string, either in PKCS#8
+/// format (`BEGIN PRIVATE KEY`) or SEC1 format (`BEGIN EC PRIVATE KEY`),
+/// as generated by `openssl ecparam -name prime256v1 -genkey`.
+///
+/// # Errors
+///
+/// Returns an `InvalidKey` error if the PEM cannot be parsed,
+/// or is not a P-256 key.
+pub fn load_signing_key(pem: &str) -> Result<SigningKey, SigningError> {
+    if let Ok(signing_key) = SigningKey::from_pkcs8_pem(pem) {
+        return Ok(signing_key);
+    }
+    match SecretKey::from_sec1_pem(pem) {
+        Ok(secret_key) => return Ok(SigningKey::from(secret_key)),
+        Err(key_error) => return Err(SigningError::InvalidKey(key_error.to_string())),
+    }
+}
+
+impl DataPackageDigest {
+    /// # Sign digest
+    ///
+    /// Signs the hash of the datapackage with `signing_key`, and adds a
+    /// [`SignedData`] block containing the signature and public key. Any
+    /// existing signature is replaced. The signature is deterministic, and
+    /// the time it was made honours `SOURCE_DATE_EPOCH`, see [`current_time`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidKey` error if the public key
+    /// cannot be encoded.
+    pub fn sign(&mut self, signing_key: &SigningKey) -> Result<(), SigningError> {
+        let signature: Signature = signing_key.sign(self.hash.as_bytes());
+        let public_key = match signing_key.verifying_key().to_public_key_der() {
+            Ok(public_key) => public_key,
+            Err(key_error) => return Err(SigningError::InvalidKey(key_error.to_string())),
+        };
+
+        self.signed_data = Some(SignedData {
+            hash: self.hash.clone(),
+            signature: BASE64.encode(signature.to_der().as_bytes()),
+            public_key: Some(BASE64.encode(public_key.as_bytes())),
+            created: current_time()
+                .with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+            software: Some(format!("wacksy {}", env!("CARGO_PKG_VERSION"))),
+            domain: None,
+            domain_cert: None,
+            time_signature: None,
+            timestamp_cert: None,
+            cross_signed_cert: None,
+        });
+        return Ok(());
+    }
+    /// # Verify digest signature
+    ///
+    /// Checks the signature in the [`SignedData`] block against the
+    /// public key included with it, and checks the signed hash is the
+    /// same as the hash of the datapackage. This does not check the
+    /// hash against the datapackage itself, see the
+    /// [validator](crate::validator) for that.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SigningError`] if the digest is not signed, was signed
+    /// in domain mode, or if the signature does not match.
+    pub fn verify(&self) -> Result<(), SigningError> {
+        let Some(signed_data) = &self.signed_data else {
+            return Err(SigningError::NotSigned);
+        };
+        if signed_data.hash != self.hash {
+            return Err(SigningError::HashMismatch {
+                expected: self.hash.clone(),
+                found: signed_data.hash.clone(),
+            });
+        }
+        let Some(public_key) = &signed_data.public_key else {
+            return Err(SigningError::UnsupportedMode);
+        };
+
+        let verifying_key = match VerifyingKey::from_public_key_der(&decode(public_key)?) {
+            Ok(verifying_key) => verifying_key,
+            Err(key_error) => return Err(SigningError::InvalidKey(key_error.to_string())),
+        };
+        // signatures are usually DER-encoded, but a raw 64 byte
+        // signature is what the Web Crypto API produces
+        let signature_bytes = decode(&signed_data.signature)?;
+        let signature = match Signature::from_der(&signature_bytes)
+            .or_else(|_| return Signature::from_slice(&signature_bytes))
+        {
+            Ok(signature) => signature,
+            Err(signature_error) => {
+                return Err(SigningError::InvalidSignature(signature_error.to_string()));
+            }
+        };
+        match verifying_key.verify(signed_data.hash.as_bytes(), &signature) {
+            Ok(()) => return Ok(()),
+            Err(signature_error) => {
+                return Err(SigningError::InvalidSignature(signature_error.to_string()));
+            }
+        }
+    }
+}
+
+fn decode(base64_string: &str) -> Result<Vec<u8>, SigningError> {
+    match BASE64.decode(base64_string) {
+        Ok(decoded_bytes) => return Ok(decoded_bytes),
+        Err(decode_error) => return Err(SigningError::EncodingError(decode_error)),
+    }
+}
+
+#[derive(Debug)]
+pub enum SigningError {
+    /// the datapackage digest has no signature
+    NotSigned,
+    /// the signed hash is not the hash of the datapackage
+    HashMismatch { expected: String, found: String },
+    /// the datapackage does not match the signed hash
+    ContentMismatch { expected: String, found: String },
+    /// domain mode signatures cannot be verified
+    UnsupportedMode,
+    /// the key could not be read or encoded
+    InvalidKey(String),
+    /// the signature is malformed or does not match
+    InvalidSignature(String),
+    /// the signature or public key is not valid base64
+    EncodingError(base64::DecodeError),
+}
+impl fmt::Display for SigningError {
+    fn fmt(&self, message: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotSigned => return write!(message, "The WACZ is not signed"),
+            Self::HashMismatch { expected, found } => {
+                return write!(
+                    message,
+                    "Signed hash does not match the datapackage digest: expected {expected} but found {found}"
+                );
+            }
+            Self::ContentMismatch { expected, found } => {
+                return write!(
+                    message,
+                    "Datapackage does not match the signed hash: expected {expected} but found {found}"
+                );
+            }
+            Self::UnsupportedMode => {
+                return write!(message, "Domain mode signatures cannot be verified");
+            }
+            Self::InvalidKey(error_message) => {
+                return write!(message, "Invalid key: {error_message}");
+            }
+            Self::InvalidSignature(error_message) => {
+                return write!(message, "Invalid signature: {error_message}");
+            }
+            Self::EncodingError(error_message) => {
+                return write!(message, "Could not decode base64: {error_message}");
+            }
+        }
+    }
+}
+impl Error for SigningError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::EncodingError(decode_error) => return Some(decode_error),
+            Self::NotSigned
+            | Self::HashMismatch { .. }
+            | Self::ContentMismatch { .. }
+            | Self::UnsupportedMode
+            | Self::InvalidKey(_)
+            | Self::InvalidSignature(_) => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn sign_and_verify_digest() {
+        let signing_key = SigningKey::from_slice(&[7_u8; 32]).unwrap();
+        let mut digest = DataPackageDigest {
+            path: "datapackage.json".to_owned(),
+            hash: "sha256:cd9b2d3bcd2ad4c4d4ba4ddb4e2d7e8ad3a6d2ae2f3ad1f37a5c0e3bc93aba4c"
+                .to_owned(),
+            signed_data: None,
+        };
+        digest.sign(&signing_key).unwrap();
+        digest.verify().unwrap();
+
+        // the signature survives a round trip through json
+        let digest: DataPackageDigest =
+            serde_json::from_str(&serde_json::to_string(&digest).unwrap()).unwrap();
+        digest.verify().unwrap();
+
+        let mut tampered_digest = digest;
+        if let Some(signed_data) = &mut tampered_digest.signed_data {
+            signed_data.hash = "sha256:0000".to_owned();
+        }
+        tampered_digest.hash = "sha256:0000".to_owned();
+        assert!(matches!(
+            tampered_digest.verify(),
+            Err(SigningError::InvalidSignature(_))
+        ));
+    }
+}
@@ -0,0 +1,494 @@
+//! Checks an existing WACZ file against the [spec](https://specs.webrecorder.net/wacz/1.1.1/).
+//!
+//! Unlike the [reader](crate::reader), which stops at the first problem,
+//! the validator carries on through the whole archive and collects
+//! everything it finds into a [`ValidationReport`]. It checks that:
+//!
+//! - all the files required by the spec are present
+//! - the hash and size of every resource match `datapackage.json`
+//! - the hash in `datapackage-digest.json` matches `datapackage.json`
+//! - the signature on the digest is valid, if the WACZ is signed and
+//!   the `signing` feature is enabled
+//! - `pages/pages.jsonl` starts with a valid header line
+//! - every line of the CDX(J) index points at a WARC record
+
+use rawzip::{ReaderAt, ZipArchive};
+use sha2::{Digest as _, Sha256};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, Read as _},
+    str::FromStr as _,
+};
+
+use crate::{
+    datapackage::{DataPackage, DataPackageDigest},
+    indexer::{CDXJIndexRecord, PageIndex},
+    reader::{self, ArchiveEntry, ReaderError},
+    replay,
+};
+
+/// The header line which should start every pages file.
+const PAGES_FORMAT: &str = "json-pages-1.0";
+
+/// Everything found while [validating](crate::WACZ::validate) a WACZ file.
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    /// The number of resources in the datapackage which were checked
+    pub resources_checked: usize,
+    /// The number of CDX(J) records which were checked
+    pub records_checked: usize,
+    /// Every problem found, in the order they were found
+    pub problems: Vec<ValidationProblem>,
+}
+impl ValidationReport {
+    /// Returns `true` if no problems were found.
+    #[must_use]
+    pub const fn is_valid(&self) -> bool {
+        return self.problems.is_empty();
+    }
+}
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, message: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            message,
+            "checked {} resources and {} records, found {} problems",
+            self.resources_checked,
+            self.records_checked,
+            self.problems.len()
+        )?;
+        for problem in &self.problems {
+            writeln!(message, "{problem}")?;
+        }
+        return Ok(());
+    }
+}
+
+/// A single problem found by the validator.
+#[derive(Debug)]
+pub enum ValidationProblem {
+    /// a file required by the spec or listed in the datapackage is not in the archive
+    MissingFile(String),
+    /// a file could not be read from the archive
+    UnreadableFile(String, ReaderError),
+    /// a file could not be parsed
+    ParsingError(String, String),
+    /// the hash of a resource does not match the datapackage
+    HashMismatch {
+        path: String,
+        expected: String,
+        found: String,
+    },
+    /// the size of a resource does not match the datapackage
+    SizeMismatch {
+        path: String,
+        expected: usize,
+        found: u64,
+    },
+    /// the hash in the datapackage digest does not match the datapackage
+    DigestMismatch { expected: String, found: String },
+    /// the signature on the datapackage digest does not match
+    InvalidSignature(String),
+    /// the first line of the pages file is not a valid header
+    InvalidPagesHeader(String),
+    /// a line of the CDX(J) index does not point at a WARC record
+    RecordNotFound { line_number: usize, reason: String },
+}
+impl fmt::Display for ValidationProblem {
+    fn fmt(&self, message: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingFile(file_path) => {
+                return write!(message, "File missing from WACZ: {file_path}");
+            }
+            Self::UnreadableFile(file_path, reader_error) => {
+                return write!(message, "Could not read {file_path}: {reader_error}");
+            }
+            Self::ParsingError(file_path, error_message) => {
+                return write!(message, "Could not parse {file_path}: {error_message}");
+            }
+            Self::HashMismatch {
+                path,
+                expected,
+                found,
+            } => {
+                return write!(
+                    message,
+                    "Hash mismatch for {path}: expected {expected} but found {found}"
+                );
+            }
+            Self::SizeMismatch {
+                path,
+                expected,
+                found,
+            } => {
+                return write!(
+                    message,
+                    "Size mismatch for {path}: expected {expected} bytes but found {found}"
+                );
+            }
+            Self::DigestMismatch { expected, found } => {
+                return write!(
+                    message,
+                    "Datapackage digest mismatch: expected {expected} but found {found}"
+                );
+            }
+            Self::InvalidSignature(error_message) => {
+                return write!(message, "Invalid signature: {error_message}");
+            }
+            Self::InvalidPagesHeader(error_message) => {
+                return write!(message, "Invalid pages header: {error_message}");
+            }
+            Self::RecordNotFound {
+                line_number,
+                reason,
+            } => {
+                return write!(
+                    message,
+                    "No WARC record for line {line_number} of the index: {reason}"
+                );
+            }
+        }
+    }
+}
+
+/// # Validate archive
+///
+/// Runs every check against the zip archive, see the [module
+/// documentation](self) for the full list.
+///
+/// # Errors
+///
+/// Returns a [`ReaderError`] only if the central directory of the zip
+/// archive cannot be read, as nothing else can be checked without it.
+/// Every other problem is added to the [`ValidationReport`].
+pub(crate) fn validate_archive<Reader: ReaderAt>(
+    archive: &ZipArchive<Reader>,
+) -> Result<ValidationReport, ReaderError> {
+    let archive_entries = reader::list_entries(archive)?;
+    let mut report = ValidationReport::default();
+
+    // check the datapackage first, as the digest and resources depend on it
+    if let Some(datapackage_bytes) =
+        read_required_file(archive, &archive_entries, "datapackage.json", &mut report)
+    {
+        check_digest(archive, &archive_entries, &datapackage_bytes, &mut report);
+        match serde_json::from_slice::<DataPackage>(&datapackage_bytes) {
+            Ok(datapackage) => {
+                check_resources(archive, &archive_entries, &datapackage, &mut report);
+            }
+            Err(serde_error) => report.problems.push(ValidationProblem::ParsingError(
+                "datapackage.json".to_owned(),
+                serde_error.to_string(),
+            )),
+        }
+    }
+
+    check_pages(archive, &archive_entries, &mut report);
+    check_index(archive, &archive_entries, &mut report);
+
+    return Ok(report);
+}
+
+/// Read a file, adding a problem to the report if it is missing or cannot be read.
+fn read_required_file<Reader: ReaderAt>(
+    archive: &ZipArchive<Reader>,
+    archive_entries: &[ArchiveEntry],
+    file_path: &str,
+    report: &mut ValidationReport,
+) -> Option<Vec<u8>> {
+    match reader::read_entry(archive, archive_entries, file_path) {
+        Ok(file_contents) => return Some(file_contents),
+        Err(ReaderError::MissingFile(file_path)) => {
+            report
+                .problems
+                .push(ValidationProblem::MissingFile(file_path));
+            return None;
+        }
+        Err(reader_error) => {
+            report.problems.push(ValidationProblem::UnreadableFile(
+                file_path.to_owned(),
+                reader_error,
+            ));
+            return None;
+        }
+    }
+}
+
+/// Check the digest file against a hash of the datapackage as it is stored.
+fn check_digest<Reader: ReaderAt>(
+    archive: &ZipArchive<Reader>,
+    archive_entries: &[ArchiveEntry],
+    datapackage_bytes: &[u8],
+    report: &mut ValidationReport,
+) {
+    let Some(digest_bytes) =
+        read_required_file(archive, archive_entries, "datapackage-digest.json", report)
+    else {
+        return;
+    };
+    match serde_json::from_slice::<DataPackageDigest>(&digest_bytes) {
+        Ok(datapackage_digest) => {
+            let datapackage_hash = format!("sha256:{:x}", Sha256::digest(datapackage_bytes));
+            #[cfg(feature = "signing")]
+            if datapackage_digest.signed_data.is_some() {
+                if let Err(signing_error) = datapackage_digest.verify() {
+                    report.problems.push(ValidationProblem::InvalidSignature(
+                        signing_error.to_string(),
+                    ));
+                }
+            }
+            if datapackage_digest.hash != datapackage_hash {
+                report.problems.push(ValidationProblem::DigestMismatch {
+                    expected: datapackage_digest.hash,
+                    found: datapackage_hash,
+                });
+            }
+        }
+        Err(serde_error) => report.problems.push(ValidationProblem::ParsingError(
+            "datapackage-digest.json".to_owned(),
+            serde_error.to_string(),
+        )),
+    }
+}
+
+/// Hash every resource in the datapackage, streaming it out
+/// of the archive so large WARC files are not held in memory.
+fn check_resources<Reader: ReaderAt>(
+    archive: &ZipArchive<Reader>,
+    archive_entries: &[ArchiveEntry],
+    datapackage: &DataPackage,
+    report: &mut ValidationReport,
+) {
+    for datapackage_resource in &datapackage.resources {
+        report.resources_checked += 1;
+        let mut hasher = Sha256::new();
+        let bytes = match reader::read_entry_with(
+            archive,
+            archive_entries,
+            &datapackage_resource.path,
+            |entry_reader| return io::copy(entry_reader, &mut hasher),
+        ) {
+            Ok(bytes) => bytes,
+            Err(ReaderError::MissingFile(file_path)) => {
+                report
+                    .problems
+                    .push(ValidationProblem::MissingFile(file_path));
+                continue;
+            }
+            Err(reader_error) => {
+                report.problems.push(ValidationProblem::UnreadableFile(
+                    datapackage_resource.path.clone(),
+                    reader_error,
+                ));
+                continue;
+            }
+        };
+
+        let hash = format!("sha256:{:x}", hasher.finalize());
+        if hash != datapackage_resource.hash {
+            report.problems.push(ValidationProblem::HashMismatch {
+                path: datapackage_resource.path.clone(),
+                expected: datapackage_resource.hash.clone(),
+                found: hash,
+            });
+        }
+        if bytes != datapackage_resource.bytes as u64 {
+            report.problems.push(ValidationProblem::SizeMismatch {
+                path: datapackage_resource.path.clone(),
+                expected: datapackage_resource.bytes,
+                found: bytes,
+            });
+        }
+    }
+}
+
+/// Check the header line of the pages file, and then the pages themselves.
+fn check_pages<Reader: ReaderAt>(
+    archive: &ZipArchive<Reader>,
+    archive_entries: &[ArchiveEntry],
+    report: &mut ValidationReport,
+) {
+    let pages_path = "pages/pages.jsonl";
+    let Some(pages_bytes) = read_required_file(archive, archive_entries, pages_path, report) else {
+        return;
+    };
+    let pages = String::from_utf8_lossy(&pages_bytes);
+
+    let header_line = pages.lines().next().unwrap_or_default();
+    let header_problem = match serde_json::from_str::<serde_json::Value>(header_line) {
+        Ok(header) => match header.get("format").and_then(serde_json::Value::as_str) {
+            Some(PAGES_FORMAT) => None,
+            Some(other_format) => Some(format!(
+                "expected format {PAGES_FORMAT}, found {other_format}"
+            )),
+            None => Some("header does not have a format".to_owned()),
+        },
+        Err(serde_error) => Some(serde_error.to_string()),
+    };
+    if let Some(header_problem) = header_problem {
+        report
+            .problems
+            .push(ValidationProblem::InvalidPagesHeader(header_problem));
+    }
+
+    if let Err(parsing_error) = PageIndex::from_str(&pages) {
+        report.problems.push(ValidationProblem::ParsingError(
+            pages_path.to_owned(),
+            parsing_error.to_string(),
+        ));
+    }
+}
+
+/// Parse the CDX(J) index line by line, and check that each line
+/// points at a WARC record in one of the WARC files.
+fn check_index<Reader: ReaderAt>(
+    archive: &ZipArchive<Reader>,
+    archive_entries: &[ArchiveEntry],
+    report: &mut ValidationReport,
+) {
+    let has_entry = |file_path: &str| {
+        return archive_entries
+            .iter()
+            .any(|archive_entry| return archive_entry.path == file_path);
+    };
+    // the index can either be plain text, or a compressed ZipNum index
+    let (index_path, index) = if has_entry("indexes/index.cdxj") {
+        let index_path = "indexes/index.cdxj";
+        let Some(index_bytes) = read_required_file(archive, archive_entries, index_path, report)
+        else {
+            return;
+        };
+        (index_path, index_bytes)
+    } else if has_entry("indexes/index.cdx.gz") {
+        let index_path = "indexes/index.cdx.gz";
+        let Some(compressed_index) =
+            read_required_file(archive, archive_entries, index_path, report)
+        else {
+            return;
+        };
+        let mut index_bytes: Vec<u8> = Vec::new();
+        if let Err(io_error) = libflate::gzip::MultiDecoder::new(compressed_index.as_slice())
+            .and_then(|mut decoder| return decoder.read_to_end(&mut index_bytes))
+        {
+            report.problems.push(ValidationProblem::UnreadableFile(
+                index_path.to_owned(),
+                ReaderError::FileReadError(io_error),
+            ));
+            return;
+        }
+        (index_path, index_bytes)
+    } else {
+        report.problems.push(ValidationProblem::MissingFile(
+            "indexes/index.cdxj".to_owned(),
+        ));
+        return;
+    };
+
+    // group the records by WARC file, so each file is only read once
+    let mut records_by_file: BTreeMap<String, Vec<(usize, CDXJIndexRecord)>> = BTreeMap::new();
+    for (line_number, line) in String::from_utf8_lossy(&index).lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match CDXJIndexRecord::from_str(line) {
+            Ok(record) => records_by_file
+                .entry(format!("archive/{}", record.filename))
+                .or_default()
+                .push((line_number + 1, record)),
+            Err(parsing_error) => report.problems.push(ValidationProblem::ParsingError(
+                index_path.to_owned(),
+                format!("line {}: {parsing_error}", line_number + 1),
+            )),
+        }
+    }
+
+    for (warc_file_path, mut records) in records_by_file {
+        report.records_checked += records.len();
+        records.sort_by_key(|(_, record)| return (record.offset, record.length));
+        let mut record_problems: Vec<ValidationProblem> = Vec::new();
+        let checked_file =
+            reader::read_entry_with(archive, archive_entries, &warc_file_path, |warc_reader| {
+                return check_records(warc_reader, &records, &mut record_problems);
+            });
+        match checked_file {
+            Ok(()) => report.problems.extend(record_problems),
+            Err(ReaderError::MissingFile(file_path)) => {
+                report
+                    .problems
+                    .push(ValidationProblem::MissingFile(file_path));
+            }
+            Err(reader_error) => {
+                report.problems.extend(record_problems);
+                report.problems.push(ValidationProblem::UnreadableFile(
+                    warc_file_path,
+                    reader_error,
+                ));
+            }
+        }
+    }
+}
+
+/// Read through a WARC file once, checking the records in order of offset.
+fn check_records(
+    warc_reader: &mut dyn io::Read,
+    records: &[(usize, CDXJIndexRecord)],
+    record_problems: &mut Vec<ValidationProblem>,
+) -> io::Result<()> {
+    let mut position: u64 = 0;
+    let mut previous_location: Option<(u64, u64, Option<String>)> = None;
+    for (line_number, record) in records {
+        // several lines can point at the same place, such as
+        // duplicate records, so reuse the result for these
+        if let Some((offset, length, reason)) = &previous_location {
+            if *offset == record.offset && *length == record.length {
+                if let Some(reason) = reason {
+                    record_problems.push(ValidationProblem::RecordNotFound {
+                        line_number: *line_number,
+                        reason: reason.clone(),
+                    });
+                }
+                continue;
+            }
+        }
+        if record.offset < position {
+            record_problems.push(ValidationProblem::RecordNotFound {
+                line_number: *line_number,
+                reason: format!(
+                    "offset {} overlaps the previous record, which ends at {position}",
+                    record.offset
+                ),
+            });
+            continue;
+        }
+
+        let skipped_bytes = io::copy(
+            &mut (&mut *warc_reader).take(record.offset - position),
+            &mut io::sink(),
+        )?;
+        let mut record_bytes: Vec<u8> = Vec::new();
+        (&mut *warc_reader)
+            .take(record.length)
+            .read_to_end(&mut record_bytes)?;
+        position += skipped_bytes + record_bytes.len() as u64;
+
+        let reason = if position < record.offset + record.length {
+            Some(format!(
+                "the WARC file ends before offset {} and length {}",
+                record.offset, record.length
+            ))
+        } else {
+            match replay::parse_warc_record(&record_bytes) {
+                Ok(_) => None,
+                Err(replay_error) => Some(replay_error.to_string()),
+            }
+        };
+        if let Some(reason) = &reason {
+            record_problems.push(ValidationProblem::RecordNotFound {
+                line_number: *line_number,
+                reason: reason.clone(),
+            });
+        }
+        previous_location = Some((record.offset, record.length, reason));
+    }
+    return Ok(());
+}
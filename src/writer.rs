@@ -0,0 +1,243 @@
+//! Writes a [`WACZ`] struct out to a zip archive.
+//!
+//! Each resource in the datapackage is copied into the archive at its
+//! path, followed by `datapackage.json` and `datapackage-digest.json`.
+//! Files are compressed as set in the [`ZipCompression`](crate::builder::ZipCompression)
+//! of the WACZ.
+//!
+//! If the WACZ was [opened](crate::WACZ::open) from an existing archive,
+//! its `datapackage.json` is written back byte for byte, unless the
+//! datapackage has been changed. A changed datapackage is given a new
+//! digest, without any signature, as that would no longer be valid.
+
+use chrono::DateTime;
+use libflate::deflate;
+use rawzip::{
+    CompressionMethod, ZipArchiveWriter, ZipDataWriter,
+    time::{UtcDateTime, ZipDateTime},
+};
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read, Write},
+};
+
+use crate::{WACZ, datapackage::HashingReader};
+
+/// # Write archive
+///
+/// Streams every file in the WACZ into a zip archive, hashing each
+/// resource as it is copied to check it has not changed since the
+/// datapackage was created. Returns the writer once the archive is finished.
+///
+/// # Errors
+///
+/// Returns a [`WriterError`] if anything goes wrong with adding files to
+/// the archive, if a resource cannot be read or does not match its hash,
+/// or if the datapackage cannot be serialised.
+pub(crate) fn write_archive<Writer: Write>(
+    wacz: &WACZ,
+    writer: Writer,
+) -> Result<Writer, WriterError> {
+    let mut archive = ZipArchiveWriter::new(writer);
+
+    // use the creation time rather than the current time, so
+    // zipping the same WACZ twice gives the same bytes
+    let modification_time = DateTime::parse_from_rfc3339(&wacz.datapackage.created)
+        .ok()
+        .map(|created| return ZipDateTime::from_unix(created.timestamp()));
+
+    // iterate over every resource in the datapackage, hashing
+    // each one as it is copied to check it has not changed
+    for datapackage_resource in &wacz.datapackage.resources {
+        let resource_reader = match datapackage_resource.content.reader() {
+            Ok(resource_reader) => resource_reader,
+            Err(io_error) => {
+                return Err(WriterError::FileError(
+                    datapackage_resource.path.clone(),
+                    io_error,
+                ));
+            }
+        };
+        let mut hashing_reader = HashingReader::new(resource_reader);
+        add_file_to_archive(
+            &mut archive,
+            wacz.compression.method_for(&datapackage_resource.path),
+            modification_time,
+            &mut hashing_reader,
+            &datapackage_resource.path,
+        )?;
+        let (hash, _) = hashing_reader.finish();
+        if hash != datapackage_resource.hash {
+            return Err(WriterError::HashMismatch {
+                path: datapackage_resource.path.clone(),
+                expected: datapackage_resource.hash.clone(),
+                found: hash,
+            });
+        }
+    }
+
+    // add datapackage file, which is only serialised again if it
+    // has been changed since it was read, along with its digest
+    let (datapackage_bytes, datapackage_digest) = wacz.datapackage_json()?;
+    add_file_to_archive(
+        &mut archive,
+        wacz.compression.method_for("datapackage.json"),
+        modification_time,
+        &mut datapackage_bytes.as_ref(),
+        "datapackage.json",
+    )?;
+
+    // add digest file
+    add_file_to_archive(
+        &mut archive,
+        wacz.compression.method_for("datapackage-digest.json"),
+        modification_time,
+        &mut serde_json::to_vec(datapackage_digest.as_ref())?.as_slice(),
+        "datapackage-digest.json",
+    )?;
+
+    // Finish the archive, which will write the central directory.
+    return Ok(archive.finish()?);
+}
+
+/// Add a single file to the archive, copying it from `file_data`.
+fn add_file_to_archive<Writer: Write>(
+    archive: &mut ZipArchiveWriter<Writer>,
+    compression_method: CompressionMethod,
+    modification_time: Option<UtcDateTime>,
+    file_data: &mut dyn Read,
+    file_path: &str,
+) -> Result<(), WriterError> {
+    if !matches!(
+        compression_method,
+        CompressionMethod::Store | CompressionMethod::Deflate
+    ) {
+        return Err(WriterError::UnsupportedCompression(
+            file_path.to_owned(),
+            compression_method,
+        ));
+    }
+
+    // Start a new file in our zip archive.
+    let mut file_builder = archive
+        .new_file(file_path)
+        .compression_method(compression_method);
+    if let Some(modification_time) = modification_time {
+        file_builder = file_builder.last_modified(modification_time);
+    }
+    let mut file = file_builder.create()?;
+
+    // Wrap the file in a ZipDataWriter, which will track information for the
+    // Zip data descriptor (like uncompressed size and crc), compressing
+    // the data on the way through if needed.
+    let copy_error = |io_error| return WriterError::FileError(file_path.to_owned(), io_error);
+    let descriptor = if compression_method == CompressionMethod::Deflate {
+        let mut writer = ZipDataWriter::new(deflate::Encoder::new(&mut file));
+        io::copy(file_data, &mut writer).map_err(copy_error)?;
+        let (encoder, descriptor) = writer.finish()?;
+        encoder.finish().into_result().map_err(copy_error)?;
+        descriptor
+    } else {
+        let mut writer = ZipDataWriter::new(&mut file);
+        io::copy(file_data, &mut writer).map_err(copy_error)?;
+        let (_, descriptor) = writer.finish()?;
+        descriptor
+    };
+
+    // Write out the data descriptor.
+    file.finish(descriptor)?;
+
+    return Ok(());
+}
+
+#[derive(Debug)]
+pub enum WriterError {
+    /// could not write the zip archive
+    ZipError(rawzip::Error),
+    /// could not copy a file into the archive
+    FileError(String, io::Error),
+    /// could not serialise the datapackage or digest to json
+    SerialisationError(serde_json::Error),
+    /// a resource has changed since the datapackage was created
+    HashMismatch {
+        path: String,
+        expected: String,
+        found: String,
+    },
+    /// the compression method is not store or deflate
+    UnsupportedCompression(String, CompressionMethod),
+}
+impl fmt::Display for WriterError {
+    fn fmt(&self, message: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZipError(error_message) => {
+                return write!(message, "Could not write zip archive: {error_message}");
+            }
+            Self::FileError(file_path, error_message) => {
+                return write!(message, "Could not copy {file_path}: {error_message}");
+            }
+            Self::SerialisationError(error_message) => {
+                return write!(message, "Serialisation error: {error_message}");
+            }
+            Self::HashMismatch {
+                path,
+                expected,
+                found,
+            } => {
+                return write!(
+                    message,
+                    "{path} does not match the hash in the datapackage, expected {expected} but got {found}"
+                );
+            }
+            Self::UnsupportedCompression(file_path, compression_method) => {
+                return write!(
+                    message,
+                    "Cannot write {file_path} with compression method {compression_method:?}"
+                );
+            }
+        }
+    }
+}
+impl Error for WriterError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::ZipError(zip_error) => return Some(zip_error),
+            Self::FileError(_, io_error) => return Some(io_error),
+            Self::SerialisationError(serde_error) => return Some(serde_error),
+            Self::HashMismatch { .. } | Self::UnsupportedCompression(..) => return None,
+        }
+    }
+}
+impl From<rawzip::Error> for WriterError {
+    fn from(zip_error: rawzip::Error) -> Self {
+        return Self::ZipError(zip_error);
+    }
+}
+impl From<serde_json::Error> for WriterError {
+    fn from(serde_error: serde_json::Error) -> Self {
+        return Self::SerialisationError(serde_error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{WaczError, builder::ZipCompression};
+
+    #[test]
+    fn unsupported_compression() {
+        let mut wacz = WACZ::from_file(std::path::Path::new("tests/example.warc.gz")).unwrap();
+        wacz.compression = ZipCompression {
+            default: CompressionMethod::Zstd,
+            entries: Vec::new(),
+        };
+        assert!(matches!(
+            wacz.zip(),
+            Err(WaczError::WriterError(WriterError::UnsupportedCompression(
+                ..
+            )))
+        ));
+    }
+}
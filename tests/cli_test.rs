@@ -0,0 +1,115 @@
+use std::{
+    env,
+    error::Error,
+    fs,
+    io::Read as _,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+use wacksy::{WACZ, datapackage::DataPackageResource};
+
+const WARC_PATH: &str = "tests/example.warc.gz";
+
+/// Run the `wacksy` binary with these arguments.
+fn wacksy(arguments: &[&str]) -> Result<Output, Box<dyn Error + Send + Sync + 'static>> {
+    return Ok(Command::new(env!("CARGO_BIN_EXE_wacksy"))
+        .args(arguments)
+        .output()?);
+}
+
+/// A path in the temporary directory which no other test uses.
+fn temporary_path(file_name: &str) -> PathBuf {
+    return env::temp_dir().join(format!("wacksy_cli_{}_{file_name}", std::process::id()));
+}
+
+#[test]
+fn create_validate_and_inspect() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let wacz_file_path = temporary_path("create.wacz");
+    let wacz_file = wacz_file_path.to_string_lossy();
+
+    let created = wacksy(&["create", WARC_PATH, "--output", &wacz_file])?;
+    assert!(created.status.success(), "{created:?}");
+
+    let validated = wacksy(&["validate", &wacz_file])?;
+    assert_eq!(validated.status.code(), Some(0), "{validated:?}");
+
+    let inspected = wacksy(&["inspect", &wacz_file])?;
+    assert_eq!(inspected.status.code(), Some(0), "{inspected:?}");
+    let summary = String::from_utf8(inspected.stdout)?;
+    assert!(summary.contains("records: 3\n"), "{summary}");
+    assert!(summary.contains("pages: 1\n"), "{summary}");
+    assert!(
+        summary.contains("archive/example.warc.gz (4599 bytes"),
+        "{summary}"
+    );
+
+    fs::remove_file(wacz_file_path)?;
+    return Ok(());
+}
+
+#[test]
+fn index_with_error_policy() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let indexed = wacksy(&["index", WARC_PATH])?;
+    assert_eq!(indexed.status.code(), Some(0), "{indexed:?}");
+    assert_eq!(
+        String::from_utf8(indexed.stdout)?,
+        fs::read_to_string(Path::new("tests/wacz_example/indexes/index.cdxj"))?
+    );
+
+    let pages = wacksy(&["index", "--pages", WARC_PATH])?;
+    assert_eq!(
+        String::from_utf8(pages.stdout)?,
+        fs::read_to_string(Path::new("tests/wacz_example/pages/pages.jsonl"))?
+    );
+
+    // every record in the example can be indexed
+    let strict = wacksy(&["index", "--error-policy", "strict", WARC_PATH])?;
+    assert_eq!(strict.status.code(), Some(0), "{strict:?}");
+    assert_eq!(
+        String::from_utf8(strict.stdout)?,
+        fs::read_to_string(Path::new("tests/wacz_example/indexes/index.cdxj"))?
+    );
+
+    // a response which is not HTTP stops a strict index with an indexing error
+    let corrupt_warc_path = temporary_path("corrupt.warc");
+    let mut warc_bytes: Vec<u8> = Vec::new();
+    libflate::gzip::MultiDecoder::new(fs::File::open(WARC_PATH)?)?.read_to_end(&mut warc_bytes)?;
+    let corrupt_warc = String::from_utf8(warc_bytes)?.replacen("HTTP/1.1 404", "HTTP/1.1 4O4", 1);
+    fs::write(&corrupt_warc_path, corrupt_warc)?;
+    let strict = wacksy(&[
+        "index",
+        "--error-policy",
+        "strict",
+        &corrupt_warc_path.to_string_lossy(),
+    ])?;
+    assert_eq!(strict.status.code(), Some(3), "{strict:?}");
+    assert!(strict.stdout.is_empty());
+
+    fs::remove_file(corrupt_warc_path)?;
+    return Ok(());
+}
+
+#[test]
+fn invalid_wacz() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let wacz_file_path = temporary_path("invalid.wacz");
+    let mut wacz_object = WACZ::from_file(Path::new(WARC_PATH))?;
+    // point the first line of the index at the wrong offset
+    let tampered_index =
+        wacz_object
+            .cdxj_index
+            .to_string()
+            .replacen("\"offset\":278,", "\"offset\":279,", 1);
+    wacz_object.datapackage.resources[1] =
+        DataPackageResource::new(Path::new("indexes/index.cdxj"), tampered_index.as_bytes())?;
+    fs::write(&wacz_file_path, wacz_object.zip()?)?;
+
+    let validated = wacksy(&["validate", &wacz_file_path.to_string_lossy()])?;
+    assert_eq!(validated.status.code(), Some(7), "{validated:?}");
+
+    // a file which is not a WACZ at all cannot be read
+    let inspected = wacksy(&["inspect", WARC_PATH])?;
+    assert_eq!(inspected.status.code(), Some(5), "{inspected:?}");
+
+    fs::remove_file(wacz_file_path)?;
+    return Ok(());
+}
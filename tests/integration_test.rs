@@ -1,7 +1,23 @@
-use std::{error::Error, fs, path::Path};
-use wacksy::indexer;
+// some tests end with an implicit `Ok(())`
+#![allow(clippy::implicit_return)]
+
+use chrono::DateTime;
+use sha2::{Digest as _, Sha256};
+use std::{
+    error::Error,
+    fs,
+    io::{Cursor, Read as _, Write as _},
+    path::{Path, PathBuf},
+};
+use wacksy::{
+    WACZ,
+    datapackage::{DataPackage, DataPackageResource, ResourceContent},
+    indexer,
+    validator::ValidationProblem,
+};
 
 const WARC_PATH: &str = "tests/example.warc.gz";
+const CREATED: &str = "2025-04-23T12:10:42+00:00";
 
 #[test]
 fn create_cdxj_index() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
@@ -14,6 +30,54 @@ fn create_cdxj_index() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
     Ok(())
 }
 
+#[test]
+fn uncompressed_record_offsets() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let warc_file_path = std::env::temp_dir().join("wacksy_uncompressed_record_offsets.warc");
+    let mut warc_file = Vec::new();
+    libflate::gzip::MultiDecoder::new(fs::File::open(WARC_PATH)?)?.read_to_end(&mut warc_file)?;
+    fs::write(&warc_file_path, &warc_file)?;
+
+    let index = indexer::Index::index_file(&warc_file_path)?;
+    for cdxj_line in index.cdxj.to_string().lines() {
+        let record: indexer::CDXJIndexRecord = cdxj_line.parse()?;
+        let record_start = usize::try_from(record.offset)?;
+        let record_end = record_start + usize::try_from(record.length)?;
+        let record_bytes = &warc_file[record_start..record_end];
+        assert!(record_bytes.starts_with(b"WARC/1."));
+        assert!(record_bytes.ends_with(b"\r\n\r\n"));
+    }
+
+    fs::remove_file(warc_file_path)?;
+    return Ok(());
+}
+
+#[test]
+fn sort_cdxj_index() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let example_cdxj_index =
+        fs::read_to_string(Path::new("tests/wacz_example/indexes/index.cdxj"))?;
+    let reversed_cdxj_index: String = example_cdxj_index
+        .lines()
+        .rev()
+        .map(|line| return format!("{line}\n"))
+        .collect();
+
+    let mut cdxj_index: indexer::CDXJIndex = reversed_cdxj_index.parse()?;
+    assert!(!cdxj_index.is_sorted());
+    cdxj_index.sort();
+    assert!(cdxj_index.is_sorted());
+    assert_eq!(cdxj_index.to_string(), example_cdxj_index);
+
+    // merging an index with itself keeps it sorted, with
+    // each pair of duplicate records next to each other
+    cdxj_index.merge(example_cdxj_index.parse()?);
+    assert!(cdxj_index.is_sorted());
+    let merged_cdxj_index = cdxj_index.to_string();
+    let merged_lines: Vec<&str> = merged_cdxj_index.lines().collect();
+    assert_eq!(merged_lines[0], merged_lines[1]);
+    assert_eq!(merged_lines[2], merged_lines[3]);
+    return Ok(());
+}
+
 #[test]
 fn create_pages_index() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
     let warc_file_path: &Path = Path::new(WARC_PATH);
@@ -25,23 +89,695 @@ fn create_pages_index() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
     Ok(())
 }
 
-// the datapackage cannot be easily tested because it contains
-// a local timestamp, how do I mock this?
-// #[test]
-// fn create_datapackage() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-//     let warc_file_path: &Path = Path::new(WARC_PATH);
-//     let index = indexer::index_file(warc_file_path)?;
-//     let warc_file = fs::read(warc_file_path)?;
+#[test]
+fn read_wacz() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let warc_file_path: &Path = Path::new(WARC_PATH);
+    let zipped_wacz = WACZ::from_file(warc_file_path)?.zip()?;
+    let wacz_object = WACZ::from_reader(Cursor::new(zipped_wacz))?;
+    let example_cdxj_index =
+        fs::read_to_string(Path::new("tests/wacz_example/indexes/index.cdxj"))?;
+    let example_pages_index =
+        fs::read_to_string(Path::new("tests/wacz_example/pages/pages.jsonl"))?;
+    assert_eq!(wacz_object.cdxj_index.to_string(), example_cdxj_index);
+    assert_eq!(wacz_object.pages_index.to_string(), example_pages_index);
+    assert_eq!(
+        wacz_object.datapackage.digest()?.hash,
+        wacz_object.datapackage_digest.hash
+    );
+    // the WARC file is read from the archive, not loaded into memory
+    let warc_resource = &wacz_object.datapackage.resources[0].content;
+    assert!(matches!(warc_resource, ResourceContent::ZipEntry(_)));
+    let mut warc_content = Vec::new();
+    warc_resource.reader()?.read_to_end(&mut warc_content)?;
+    assert_eq!(warc_content, fs::read(warc_file_path)?);
+    let mut warc_tail = Vec::new();
+    warc_resource
+        .reader_from(278)?
+        .read_to_end(&mut warc_tail)?;
+    assert_eq!(warc_tail, warc_content[278..]);
+    return Ok(());
+}
+
+#[test]
+fn read_wacz_with_zipnum_index() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let warc_file_paths = [Path::new(WARC_PATH).to_path_buf()];
+    let zipped_wacz = WACZ::from_files_with_index_format(
+        &warc_file_paths,
+        indexer::IndexFormat::ZipNum { lines_per_block: 1 },
+    )?
+    .zip()?;
+    let wacz_object = WACZ::from_reader(Cursor::new(zipped_wacz))?;
+    let resource_paths: Vec<&str> = wacz_object
+        .datapackage
+        .resources
+        .iter()
+        .map(|resource| return resource.path.as_str())
+        .collect();
+    assert_eq!(
+        resource_paths,
+        [
+            "archive/example.warc.gz",
+            "indexes/index.cdx.gz",
+            "indexes/index.idx",
+            "pages/pages.jsonl"
+        ]
+    );
+    let example_cdxj_index =
+        fs::read_to_string(Path::new("tests/wacz_example/indexes/index.cdxj"))?;
+    assert_eq!(wacz_object.cdxj_index.to_string(), example_cdxj_index);
+    return Ok(());
+}
+
+#[test]
+fn write_wacz_to_file() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let warc_file_path: &Path = Path::new(WARC_PATH);
+    let wacz_file_path = std::env::temp_dir().join("wacksy_write_wacz_to_file.wacz");
+    let wacz_object = WACZ::from_file(warc_file_path)?;
+    wacz_object.write_to(fs::File::create(&wacz_file_path)?)?;
+    let written_wacz_object = WACZ::open(&wacz_file_path)?;
+    assert_eq!(
+        written_wacz_object.datapackage_digest.hash,
+        wacz_object.datapackage_digest.hash
+    );
+    assert_eq!(fs::read(&wacz_file_path)?, wacz_object.zip()?);
+    fs::remove_file(wacz_file_path)?;
+    return Ok(());
+}
+
+#[test]
+fn create_wacz_from_multiple_files() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let temp_dir = std::env::temp_dir().join("wacksy_create_wacz_from_multiple_files");
+    fs::create_dir_all(&temp_dir)?;
+    let warc_file_paths = vec![
+        temp_dir.join("crawl-0.warc.gz"),
+        temp_dir.join("crawl-1.warc.gz"),
+    ];
+    for warc_file_path in &warc_file_paths {
+        fs::copy(WARC_PATH, warc_file_path)?;
+    }
+
+    let wacz_object = WACZ::from_files(&warc_file_paths)?;
+    let resource_paths: Vec<&str> = wacz_object
+        .datapackage
+        .resources
+        .iter()
+        .map(|resource| return resource.path.as_str())
+        .collect();
+    assert_eq!(
+        resource_paths,
+        [
+            "archive/crawl-0.warc.gz",
+            "archive/crawl-1.warc.gz",
+            "indexes/index.cdxj",
+            "pages/pages.jsonl"
+        ]
+    );
+
+    // each record appears once for each file, sorted together
+    let cdxj_index = wacz_object.cdxj_index.to_string();
+    let cdxj_lines: Vec<&str> = cdxj_index.lines().collect();
+    assert_eq!(cdxj_lines.len(), 6);
+    assert!(
+        cdxj_lines[0].starts_with("com,example)/ ") && cdxj_lines[1].starts_with("com,example)/ ")
+    );
+    assert!(cdxj_lines[0].contains("\"filename\":\"crawl-0.warc.gz\""));
+    assert!(cdxj_lines[1].contains("\"filename\":\"crawl-1.warc.gz\""));
+    assert_eq!(wacz_object.pages_index.to_string().lines().count(), 3);
+
+    fs::remove_dir_all(temp_dir)?;
+    return Ok(());
+}
+
+#[test]
+fn fetch_record_from_wacz() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let zipped_wacz = WACZ::from_file(Path::new(WARC_PATH))?.zip()?;
+    let wacz_object = WACZ::from_reader(Cursor::new(zipped_wacz))?;
+
+    let query = indexer::CDXJQuery::new("https://example.com/favicon.ico");
+    let cdxj_records = wacz_object.cdxj_index.query(&query)?;
+    assert_eq!(cdxj_records.len(), 1);
+
+    let archived_response = wacz_object.fetch_record(cdxj_records[0])?;
+    assert_eq!(archived_response.status, 404);
+    assert_eq!(
+        archived_response.header("Content-Type"),
+        Some(b"text/html".as_slice())
+    );
+    assert_eq!(
+        archived_response
+            .record
+            .header(warc::WarcHeader::TargetURI)
+            .as_deref(),
+        Some("https://example.com/favicon.ico")
+    );
+    assert!(!archived_response.body.is_empty());
+
+    // a record past the end of the WARC file is not found
+    let damaged_line = cdxj_records[0].to_string().replacen(
+        &format!("\"length\":{},", cdxj_records[0].length),
+        &format!("\"length\":{},", u64::MAX),
+        1,
+    );
+    let damaged_record: indexer::CDXJIndexRecord = damaged_line.parse()?;
+    assert!(wacz_object.fetch_record(&damaged_record).is_err());
+    return Ok(());
+}
+
+#[test]
+fn validate_wacz() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let wacz_file_path = std::env::temp_dir().join("wacksy_validate_wacz.wacz");
+    let mut wacz_object = WACZ::from_file(Path::new(WARC_PATH))?;
+    fs::write(&wacz_file_path, wacz_object.zip()?)?;
+
+    let validation_report = WACZ::validate(&wacz_file_path)?;
+    assert!(validation_report.is_valid(), "{validation_report}");
+    assert_eq!(validation_report.resources_checked, 3);
+    assert_eq!(validation_report.records_checked, 3);
+
+    // point the first line of the index at the wrong offset, the
+    // datapackage digest is made again to match the changed index
+    let tampered_index =
+        wacz_object
+            .cdxj_index
+            .to_string()
+            .replacen("\"offset\":278,", "\"offset\":279,", 1);
+    wacz_object.datapackage.resources[1] =
+        DataPackageResource::new(Path::new("indexes/index.cdxj"), tampered_index.as_bytes())?;
+    fs::write(&wacz_file_path, wacz_object.zip()?)?;
+
+    let validation_report = WACZ::validate(&wacz_file_path)?;
+    assert!(matches!(
+        validation_report.problems.as_slice(),
+        [ValidationProblem::RecordNotFound { line_number: 1, .. }]
+    ));
+
+    fs::remove_file(wacz_file_path)?;
+    return Ok(());
+}
+
+/// Zip up files the way another tool might, without going through a [WACZ].
+fn zip_files(
+    archive_files: impl IntoIterator<Item = (&'static str, Vec<u8>)>,
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync + 'static>> {
+    let mut archive = rawzip::ZipArchiveWriter::new(Vec::new());
+    for (file_path, file_contents) in archive_files {
+        let mut file = archive.new_file(file_path).create()?;
+        let mut writer = rawzip::ZipDataWriter::new(&mut file);
+        writer.write_all(&file_contents)?;
+        let (_, descriptor) = writer.finish()?;
+        file.finish(descriptor)?;
+    }
+    return Ok(archive.finish()?);
+}
+
+#[test]
+fn rezip_opened_wacz() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    // write a WACZ the way another tool might, with an indented
+    // datapackage holding a field this library does not know about
+    let wacz_object = WACZ::builder()
+        .warc_file(Path::new(WARC_PATH))
+        .created(DateTime::parse_from_rfc3339(CREATED)?)
+        .build()?;
+    let mut datapackage = serde_json::to_value(&wacz_object.datapackage)?;
+    datapackage["extras"] = serde_json::json!({ "crawlId": "example" });
+    let datapackage_bytes = serde_json::to_vec_pretty(&datapackage)?;
+    let datapackage_digest = format!(
+        "{{\"path\":\"datapackage.json\",\"hash\":\"sha256:{:x}\"}}",
+        Sha256::digest(&datapackage_bytes)
+    );
+    let archive_files = [
+        ("archive/example.warc.gz", fs::read(WARC_PATH)?),
+        (
+            "indexes/index.cdxj",
+            wacz_object.cdxj_index.to_string().into_bytes(),
+        ),
+        (
+            "pages/pages.jsonl",
+            wacz_object.pages_index.to_string().into_bytes(),
+        ),
+        ("datapackage.json", datapackage_bytes.clone()),
+        ("datapackage-digest.json", datapackage_digest.into_bytes()),
+    ];
+    let zipped_wacz = zip_files(archive_files)?;
+
+    // the datapackage is written back as it was, so it still matches its digest
+    let wacz_file_path = std::env::temp_dir().join("wacksy_rezip_opened_wacz.wacz");
+    let mut opened_wacz = WACZ::from_reader(Cursor::new(zipped_wacz))?;
+    let rezipped_wacz = opened_wacz.zip()?;
+    assert!(
+        rezipped_wacz
+            .windows(datapackage_bytes.len())
+            .any(|window| return window == datapackage_bytes)
+    );
+    fs::write(&wacz_file_path, rezipped_wacz)?;
+    let validation_report = WACZ::validate(&wacz_file_path)?;
+    assert!(validation_report.is_valid(), "{validation_report}");
+
+    // a changed datapackage gets a new digest
+    opened_wacz.datapackage.title = Some("Changed title".to_owned());
+    fs::write(&wacz_file_path, opened_wacz.zip()?)?;
+    let validation_report = WACZ::validate(&wacz_file_path)?;
+    assert!(validation_report.is_valid(), "{validation_report}");
+
+    fs::remove_file(wacz_file_path)?;
+    return Ok(());
+}
+
+#[cfg(feature = "signing")]
+#[test]
+fn sign_and_verify_wacz() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let signing_key = wacksy::signing::SigningKey::from_slice(&[7_u8; 32])?;
+    let mut wacz_object = WACZ::from_file(Path::new(WARC_PATH))?;
+    wacz_object.sign(&signing_key)?;
+
+    let signed_wacz = WACZ::from_reader(Cursor::new(wacz_object.zip()?))?;
+    signed_wacz.verify_signature()?;
+
+    // a datapackage changed after signing no longer matches the signed hash
+    let mut tampered_datapackage = serde_json::to_value(&wacz_object.datapackage)?;
+    tampered_datapackage["title"] = serde_json::json!("Changed title");
+    let tampered_wacz = zip_files([
+        ("archive/example.warc.gz", fs::read(WARC_PATH)?),
+        (
+            "indexes/index.cdxj",
+            wacz_object.cdxj_index.to_string().into_bytes(),
+        ),
+        (
+            "pages/pages.jsonl",
+            wacz_object.pages_index.to_string().into_bytes(),
+        ),
+        (
+            "datapackage.json",
+            serde_json::to_vec(&tampered_datapackage)?,
+        ),
+        (
+            "datapackage-digest.json",
+            serde_json::to_vec(&wacz_object.datapackage_digest)?,
+        ),
+    ])?;
+    let tampered_wacz = WACZ::from_reader(Cursor::new(tampered_wacz))?;
+    assert!(matches!(
+        tampered_wacz.verify_signature(),
+        Err(wacksy::WaczError::SigningError(
+            wacksy::signing::SigningError::ContentMismatch { .. }
+        ))
+    ));
+
+    wacz_object.datapackage.title = Some("Changed title".to_owned());
+    assert!(wacz_object.verify_signature().is_err());
+    return Ok(());
+}
+
+#[test]
+fn create_datapackage() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let warc_file_paths = [PathBuf::from(WARC_PATH)];
+    let index = indexer::Index::index_files(&warc_file_paths)?;
+    let data_package = DataPackage::builder(&warc_file_paths, &index)
+        .created(DateTime::parse_from_rfc3339(CREATED)?)
+        .build()?;
+
+    let generated_data_package = serde_json::to_string(&data_package)?;
+    let example_data_package =
+        fs::read_to_string(Path::new("tests/wacz_example/datapackage.json"))?;
+    assert_eq!(generated_data_package, example_data_package);
+
+    let generated_digest = serde_json::to_string(&data_package.digest()?)?;
+    let example_digest =
+        fs::read_to_string(Path::new("tests/wacz_example/datapackage-digest.json"))?;
+    assert_eq!(generated_digest, example_digest);
+    return Ok(());
+}
+
+#[test]
+fn reproducible_wacz() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let zip_wacz = || -> Result<Vec<u8>, Box<dyn Error + Send + Sync + 'static>> {
+        let wacz_object = WACZ::builder()
+            .warc_file(Path::new(WARC_PATH))
+            .created(DateTime::parse_from_rfc3339(CREATED)?)
+            .build()?;
+        return Ok(wacz_object.zip()?);
+    };
+
+    assert_eq!(zip_wacz()?, zip_wacz()?);
+    return Ok(());
+}
+
+#[test]
+fn build_wacz_with_options() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let wacz_object = WACZ::builder()
+        .warc_reader("example.warc.gz", fs::File::open(WARC_PATH)?)
+        .title("Example Domain")
+        .compression_method("indexes/", rawzip::CompressionMethod::Deflate)
+        .created(DateTime::parse_from_rfc3339(CREATED)?)
+        .build()?;
+    let wacz_from_file = WACZ::from_file(Path::new(WARC_PATH))?;
+    assert_eq!(
+        wacz_object.datapackage.resources[0].hash,
+        wacz_from_file.datapackage.resources[0].hash
+    );
+    assert_eq!(
+        wacz_object.cdxj_index.to_string(),
+        wacz_from_file.cdxj_index.to_string()
+    );
+
+    // the deflated index reads back the same
+    let read_wacz = WACZ::from_reader(Cursor::new(wacz_object.zip()?))?;
+    assert_eq!(
+        read_wacz.datapackage.title.as_deref(),
+        Some("Example Domain")
+    );
+    assert_eq!(
+        read_wacz.cdxj_index.to_string(),
+        wacz_from_file.cdxj_index.to_string()
+    );
+    return Ok(());
+}
+
+#[test]
+fn collect_indexing_warnings() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let index = indexer::Index::index_file(Path::new(WARC_PATH))?;
+    let first_warning = &index.warnings[0];
+    assert_eq!(first_warning.warc_file_name, "example.warc.gz");
+    assert_eq!(first_warning.record_number, 1);
+    assert_eq!(
+        first_warning
+            .location
+            .map(|location| return location.offset),
+        Some(0)
+    );
+    assert_eq!(first_warning.skipped_from, indexer::SkippedFrom::CdxjIndex);
+    assert!(matches!(
+        first_warning.error,
+        indexer::IndexingError::UnindexableRecordType(warc::RecordType::WarcInfo)
+    ));
+    assert!(
+        index
+            .warnings
+            .iter()
+            .any(|warning| return warning.skipped_from == indexer::SkippedFrom::PageIndex)
+    );
+    return Ok(());
+}
+
+#[test]
+fn index_with_error_policy() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    // every record in the example can be indexed, including
+    // the urn:pageinfo resource, which has no HTTP response
+    let strict_options = indexer::IndexOptions {
+        error_policy: indexer::ErrorPolicy::Strict,
+        ..Default::default()
+    };
+    let strict_index =
+        indexer::Index::index_file_with_options(Path::new(WARC_PATH), &strict_options)?;
+    let page_info = strict_index.cdxj.query(&indexer::CDXJQuery::new(
+        "urn:pageinfo:https://example.com/",
+    ))?;
+    assert_eq!(page_info.len(), 1);
+    assert_eq!(
+        page_info[0]
+            .mime
+            .as_ref()
+            .map(|mime| return mime.mime.as_str()),
+        Some("application/json")
+    );
+    assert!(page_info[0].status.is_none());
 
-//     let cdxj_index_bytes = index.0.to_string().into_bytes();
-//     let pages_index_bytes = index.1.to_string().into_bytes();
+    // a response without an HTTP status line stops a strict index
+    let mut warc_bytes: Vec<u8> = Vec::new();
+    libflate::gzip::MultiDecoder::new(fs::File::open(WARC_PATH)?)?.read_to_end(&mut warc_bytes)?;
+    let corrupt_warc =
+        String::from_utf8(warc_bytes.clone())?.replacen("HTTP/1.1 404", "HTTP/1.1 4O4", 1);
+    let strict_index = indexer::Index::index_reader_with_options(
+        corrupt_warc.as_bytes(),
+        Path::new("example.warc"),
+        &strict_options,
+    );
+    assert!(matches!(
+        strict_index,
+        Err(indexer::IndexingError::RecordError(ref indexing_warning))
+            if matches!(indexing_warning.error, indexer::IndexingError::HttpResponseError(_))
+    ));
 
-//     let data_package =
-//         datapackage::DataPackage::new(&warc_file, &cdxj_index_bytes, &pages_index_bytes)?;
+    // break the content length of the first response in an uncompressed copy
+    let content_length = b"Content-Length: 1653";
+    let Some(position) = warc_bytes
+        .windows(content_length.len())
+        .position(|window| return window == content_length)
+    else {
+        return Err("no content length in example WARC".into());
+    };
+    warc_bytes[position + 16..position + 20].copy_from_slice(b"9999");
 
-//     let generated_data_package = serde_json::to_string(&data_package)?;
-//     let example_data_package =
-//         fs::read_to_string(Path::new("tests/wacz_example/datapackage.json"))?;
-//     assert_eq!(generated_data_package, example_data_package);
-//     Ok(())
-// }
+    assert!(
+        indexer::Index::index_reader(warc_bytes.as_slice(), Path::new("example.warc")).is_err()
+    );
+    let resync_options = indexer::IndexOptions {
+        error_policy: indexer::ErrorPolicy::Resync,
+        ..Default::default()
+    };
+    let resync_index = indexer::Index::index_reader_with_options(
+        warc_bytes.as_slice(),
+        Path::new("example.warc"),
+        &resync_options,
+    )?;
+    assert!(resync_index.warnings.iter().any(|indexing_warning| {
+        return matches!(
+            indexing_warning.error,
+            indexer::IndexingError::DamagedRecord(_)
+        );
+    }));
+    // the second response is still indexed
+    assert_eq!(
+        resync_index
+            .cdxj
+            .query(&indexer::CDXJQuery::new("https://example.com/favicon.ico"))?
+            .len(),
+        1
+    );
+    return Ok(());
+}
+
+#[test]
+fn index_post_request() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    fn warc_record(warc_headers: &str, http_message: &str) -> String {
+        return format!(
+            "WARC/1.1\r\n{warc_headers}WARC-Date: 2025-04-23T12:10:42Z\r\nWARC-Target-URI: https://example.com/search?lang=en\r\nContent-Length: {}\r\n\r\n{http_message}\r\n\r\n",
+            http_message.len()
+        );
+    }
+    let response = warc_record(
+        "WARC-Type: response\r\nWARC-Record-ID: <urn:uuid:1>\r\nWARC-Payload-Digest: sha256:0000\r\nContent-Type: application/http; msgtype=response\r\n",
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\r\n<p>results</p>",
+    );
+    let request = warc_record(
+        "WARC-Type: request\r\nWARC-Record-ID: <urn:uuid:2>\r\nWARC-Concurrent-To: <urn:uuid:1>\r\nContent-Type: application/http; msgtype=request\r\n",
+        "POST /search?lang=en HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/x-www-form-urlencoded\r\n\r\nq=web+archive",
+    );
+    let warc = format!("{response}{request}");
+
+    let index = indexer::Index::index_reader(warc.as_bytes(), Path::new("post.warc"))?;
+    let cdxj_index = index.cdxj.to_string();
+    let lines: Vec<&str> = cdxj_index.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|line| {
+        return line.starts_with("com,example)/search?__wb_method=post&lang=en&q=web+archive ")
+            && line.contains("\"method\":\"POST\"")
+            && line.contains("\"requestBody\":\"__wb_method=post&q=web+archive\"");
+    }));
+    assert!(lines[1].contains("\"mime\":\"warc/request\""));
+    return Ok(());
+}
+
+#[test]
+fn fetch_post_response() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    fn warc_record(warc_headers: &str, http_message: &str) -> String {
+        return format!(
+            "WARC/1.1\r\n{warc_headers}WARC-Date: 2025-04-23T12:10:42Z\r\nWARC-Target-URI: https://example.com/search\r\nContent-Length: {}\r\n\r\n{http_message}\r\n\r\n",
+            http_message.len()
+        );
+    }
+    // some writers, such as wget, write the request before the response
+    let request = warc_record(
+        "WARC-Type: request\r\nWARC-Record-ID: <urn:uuid:1>\r\nWARC-Concurrent-To: <urn:uuid:2>\r\nContent-Type: application/http; msgtype=request\r\n",
+        "POST /search HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/x-www-form-urlencoded\r\n\r\nq=web+archive",
+    );
+    let response = warc_record(
+        "WARC-Type: response\r\nWARC-Record-ID: <urn:uuid:2>\r\nWARC-Payload-Digest: sha256:0000\r\nContent-Type: application/http; msgtype=response\r\n",
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\r\n<p>results</p>",
+    );
+    let wacz_object = WACZ::builder()
+        .warc_reader("post.warc", Cursor::new(format!("{request}{response}")))
+        .build()?;
+    assert!(
+        wacz_object
+            .cdxj_index
+            .to_string()
+            .lines()
+            .next()
+            .is_some_and(|line| return line.contains("\"mime\":\"warc/request\""))
+    );
+
+    // only the response is found, as the request has nothing to replay
+    let query =
+        indexer::CDXJQuery::new("https://example.com/search?__wb_method=post&q=web+archive");
+    let cdxj_records = wacz_object.cdxj_index.query(&query)?;
+    assert_eq!(cdxj_records.len(), 1);
+    let archived_response = wacz_object.fetch_record(cdxj_records[0])?;
+    assert_eq!(archived_response.status, 200);
+
+    // the request can still be found by its media type, but not fetched
+    let request_records = wacz_object.cdxj_index.query(&query.mime("warc/request"))?;
+    assert_eq!(request_records.len(), 1);
+    assert!(matches!(
+        wacz_object.fetch_record(request_records[0]),
+        Err(wacksy::WaczError::ReplayError(
+            wacksy::replay::ReplayError::RequestRecord(_)
+        ))
+    ));
+    return Ok(());
+}
+
+#[test]
+fn pair_requests_across_files() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    fn warc_record(warc_headers: &str, http_message: &str) -> String {
+        return format!(
+            "WARC/1.1\r\n{warc_headers}WARC-Date: 2025-04-23T12:10:42Z\r\nWARC-Target-URI: https://example.com/search\r\nContent-Length: {}\r\n\r\n{http_message}\r\n\r\n",
+            http_message.len()
+        );
+    }
+    // the response is at the end of one WARC file,
+    // and its request at the start of the next
+    let temp_dir = std::env::temp_dir().join("wacksy_pair_requests_across_files");
+    fs::create_dir_all(&temp_dir)?;
+    let warc_file_paths = vec![temp_dir.join("crawl-0.warc"), temp_dir.join("crawl-1.warc")];
+    fs::write(
+        &warc_file_paths[0],
+        warc_record(
+            "WARC-Type: response\r\nWARC-Record-ID: <urn:uuid:1>\r\nWARC-Payload-Digest: sha256:0000\r\nContent-Type: application/http; msgtype=response\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n<p>results</p>",
+        ),
+    )?;
+    fs::write(
+        &warc_file_paths[1],
+        warc_record(
+            "WARC-Type: request\r\nWARC-Record-ID: <urn:uuid:2>\r\nWARC-Concurrent-To: <urn:uuid:1>\r\nContent-Type: application/http; msgtype=request\r\n",
+            "POST /search HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\n\r\nq=web+archive",
+        ),
+    )?;
+
+    let index = indexer::Index::index_files(&warc_file_paths)?;
+    let cdxj_index = index.cdxj.to_string();
+    assert!(cdxj_index.lines().any(|line| {
+        return line.starts_with("com,example)/search?__wb_method=post&q=web+archive ")
+            && line.contains("\"filename\":\"crawl-0.warc\"")
+            && line.contains("\"status\":200");
+    }));
+    assert!(index.cdxj.is_sorted());
+
+    fs::remove_dir_all(temp_dir)?;
+    return Ok(());
+}
+
+#[test]
+fn resolve_revisit_records() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    fn warc_record(warc_headers: &str, http_message: &str) -> String {
+        return format!(
+            "WARC/1.1\r\n{warc_headers}WARC-Target-URI: https://example.com/\r\nWARC-Payload-Digest: sha256:0000\r\nContent-Length: {}\r\n\r\n{http_message}\r\n\r\n",
+            http_message.len()
+        );
+    }
+    let response = warc_record(
+        "WARC-Type: response\r\nWARC-Record-ID: <urn:uuid:1>\r\nWARC-Date: 2025-04-23T12:10:42Z\r\n",
+        "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\n\r\n<p>example</p>",
+    );
+    let revisit = warc_record(
+        "WARC-Type: revisit\r\nWARC-Record-ID: <urn:uuid:2>\r\nWARC-Date: 2025-05-01T09:00:00Z\r\nWARC-Profile: http://netpreserve.org/warc/1.1/revisit/identical-payload-digest\r\nWARC-Refers-To-Target-URI: https://example.com/\r\nWARC-Refers-To-Date: 2025-04-23T12:10:42Z\r\n",
+        "",
+    );
+    let warc = format!("{response}{revisit}");
+
+    let unresolved_index =
+        indexer::Index::index_reader(warc.as_bytes(), Path::new("revisit.warc"))?;
+    let unresolved_line = unresolved_index.cdxj.to_string();
+    assert!(unresolved_line.lines().nth(1).is_some_and(|line| {
+        return line.contains("\"mime\":\"warc/revisit\"")
+            && line.contains("\"refersToDate\":\"20250423121042\"")
+            && !line.contains("\"status\"");
+    }));
+
+    let options = indexer::IndexOptions {
+        resolve_revisits: true,
+        ..Default::default()
+    };
+    let index = indexer::Index::index_reader_with_options(
+        warc.as_bytes(),
+        Path::new("revisit.warc"),
+        &options,
+    )?;
+    let cdxj_index = index.cdxj.to_string();
+    let Some(revisit_line) = cdxj_index.lines().nth(1) else {
+        return Err("revisit record not indexed".into());
+    };
+    assert!(revisit_line.contains("\"status\":200"));
+    assert!(
+        revisit_line.contains(
+            "\"origMime\":\"text/html\",\"origFilename\":\"revisit.warc\",\"origOffset\":0"
+        )
+    );
+
+    // the revisit fields survive being read back in
+    let read_index: indexer::CDXJIndex = cdxj_index.parse()?;
+    assert_eq!(read_index.to_string(), cdxj_index);
+    return Ok(());
+}
+
+#[test]
+fn index_revisit_with_malformed_headers() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let response = "WARC/1.1\r\nWARC-Type: response\r\nWARC-Record-ID: <urn:uuid:1>\r\nWARC-Date: 2025-04-23T12:10:42Z\r\nWARC-Target-URI: https://example.com/\r\nWARC-Payload-Digest: sha256:0000\r\nContent-Length: 44\r\n\r\nHTTP/1.1 200 OK\r\ncontent-type: text/html\r\n\r\n\r\n\r\n";
+    let revisit = "WARC/1.1\r\nWARC-Type: revisit\r\nWARC-Record-ID: <urn:uuid:2>\r\nWARC-Date: 2025-05-01T09:00:00Z\r\nWARC-Target-URI: https://example.com/\r\nWARC-Payload-Digest: sha256:0000\r\nWARC-Refers-To-Target-URI: not a url\r\nWARC-Refers-To-Date: last week\r\nContent-Length: 0\r\n\r\n\r\n\r\n";
+    let warc = format!("{response}{revisit}");
+
+    let options = indexer::IndexOptions {
+        error_policy: indexer::ErrorPolicy::Strict,
+        resolve_revisits: true,
+        ..Default::default()
+    };
+    let index = indexer::Index::index_reader_with_options(
+        warc.as_bytes(),
+        Path::new("revisit.warc"),
+        &options,
+    )?;
+    let header_warnings = index
+        .warnings
+        .iter()
+        .filter(|warning| return warning.skipped_from == indexer::SkippedFrom::RevisitHeader)
+        .count();
+    assert_eq!(header_warnings, 2);
+
+    // the revisit is still indexed, and resolved by its digest instead
+    let cdxj_index = index.cdxj.to_string();
+    let Some(revisit_line) = cdxj_index.lines().nth(1) else {
+        return Err("revisit record not indexed".into());
+    };
+    assert!(!revisit_line.contains("refersTo"));
+    assert!(revisit_line.contains("\"origOffset\":0"));
+    return Ok(());
+}
+
+#[test]
+fn index_record_without_content_type() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let http_message =
+        "HTTP/1.1 200 OK\r\nContent-Length: 30\r\n\r\n<!DOCTYPE html><title>a</title>";
+    let warc = format!(
+        "WARC/1.1\r\nWARC-Type: response\r\nWARC-Record-ID: <urn:uuid:1>\r\nWARC-Date: 2025-04-23T12:10:42Z\r\nWARC-Target-URI: https://example.com/\r\nWARC-Payload-Digest: sha256:0000\r\nContent-Type: application/http; msgtype=response\r\nContent-Length: {}\r\n\r\n{http_message}\r\n\r\n",
+        http_message.len()
+    );
+
+    let index = indexer::Index::index_reader(warc.as_bytes(), Path::new("sniffed.warc"))?;
+    let cdxj_index = index.cdxj.to_string();
+    assert!(cdxj_index.contains("\"mime\":\"text/html\""));
+    assert!(cdxj_index.contains("\"sniffedMime\":\"text/html\""));
+    assert!(!cdxj_index.contains("declaredMime"));
+    assert_eq!(index.pages.into_iter().count(), 1);
+    return Ok(());
+}